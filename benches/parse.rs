@@ -0,0 +1,56 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use testcase_markdown::{get_test_cases, MergeSerialized};
+use toml::{from_str, Table};
+
+#[derive(Default, Clone)]
+struct Options {
+    foo: i64,
+    bar: bool,
+}
+
+impl MergeSerialized for Options {
+    fn merge_serialized(&self, source: String) -> Result<Self, String> {
+        let values = from_str::<Table>(&source).map_err(|e| e.to_string())?;
+        Ok(Options {
+            foo: values
+                .get("foo")
+                .and_then(|v| v.as_integer())
+                .unwrap_or(self.foo),
+            bar: values
+                .get("bar")
+                .and_then(|v| v.as_bool())
+                .unwrap_or(self.bar),
+        })
+    }
+}
+
+/// Generates a document with 100 groups of 100 test cases each, with an
+/// options block on every group and on every tenth test case.
+fn generate(groups: usize, cases: usize) -> String {
+    let mut content = String::from("# Tests\n\n```toml options\nfoo = 1\n```\n\n");
+    for group in 0..groups {
+        content.push_str(&format!(
+            "## Group {group}\n\n```toml options\nbar = true\n```\n\n"
+        ));
+        for case in 0..cases {
+            content.push_str(&format!("### Case {case}\n\n"));
+            if case % 10 == 0 {
+                content.push_str("```toml options\nfoo = 2\n```\n\n");
+            }
+            content.push_str(&format!(
+                "```\ninput {case}\n```\n\n```\noutput {case}\n```\n\n"
+            ));
+        }
+    }
+    content
+}
+
+fn parse(c: &mut Criterion) {
+    let content = generate(100, 100);
+    c.bench_function("parse 10k test cases", |b| {
+        b.iter(|| get_test_cases(black_box(content.clone()), Options::default()).unwrap())
+    });
+}
+
+criterion_group!(benches, parse);
+criterion_main!(benches);
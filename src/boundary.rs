@@ -0,0 +1,61 @@
+use markdown::mdast::Node;
+use std::{fmt, sync::Arc};
+
+/// Decides which nodes end the current test case. See [`crate::Config::boundary`].
+///
+/// A heading which isn't a boundary is treated as prose: it neither starts a
+/// new section nor ends the current test case. Any other node which is a
+/// boundary ends the current test case, like a thematic break with
+/// [`crate::Config::split_on_thematic_break`], and is then handled as usual.
+///
+/// Nodes come from the `markdown` crate's syntax tree, re-exported as
+/// [`crate::Node`]. Closures taking a `&Node` implement this trait too.
+pub trait Boundary {
+    fn is_boundary(&self, node: &Node) -> bool;
+}
+
+impl<F: Fn(&Node) -> bool> Boundary for F {
+    fn is_boundary(&self, node: &Node) -> bool {
+        self(node)
+    }
+}
+
+/// The default boundary: every heading starts a new test case.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct HeadingBoundary;
+
+impl Boundary for HeadingBoundary {
+    fn is_boundary(&self, node: &Node) -> bool {
+        matches!(node, Node::Heading(_))
+    }
+}
+
+/// Ends a test case at every heading and every thematic break (`---`), so
+/// that one heading can hold several test cases.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ThematicBreakBoundary;
+
+impl Boundary for ThematicBreakBoundary {
+    fn is_boundary(&self, node: &Node) -> bool {
+        matches!(node, Node::Heading(_) | Node::ThematicBreak(_))
+    }
+}
+
+/// A shared [`Boundary`] for [`crate::Config::boundary`]. Boundaries are
+/// compared by identity.
+#[derive(Clone)]
+pub struct SharedBoundary(pub Arc<dyn Boundary + Send + Sync>);
+
+impl fmt::Debug for SharedBoundary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SharedBoundary(..)")
+    }
+}
+
+impl PartialEq for SharedBoundary {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for SharedBoundary {}
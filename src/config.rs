@@ -0,0 +1,266 @@
+use crate::{Boundary, HeadingBoundary, SharedBoundary};
+use markdown::{mdast::Node, Constructs, ParseOptions};
+
+/// Settings which control how test cases are extracted from markdown.
+///
+/// Each code block is classified by the first of these rules which applies:
+///
+/// 1. A block with `literal` in its meta is an arg.
+/// 2. A block with the options keyword in its meta is options, whatever its
+///    language. With [`Config::options_by_lang`], so is a block whose language
+///    is the options keyword.
+/// 3. A block with the language `setup`, or `setup` in its meta, is setup.
+/// 4. Anything else is an arg which keeps its language, including languages
+///    like `yaml`, `diff` or `options` which might look special.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Config {
+    /// Code blocks whose meta contains this keyword are treated as options
+    /// rather than args. Defaults to `"options"`.
+    pub options_keyword: String,
+    /// Whether to parse YAML (`---`) or TOML (`+++`) frontmatter at the top of
+    /// the document and merge it into the root options. Defaults to `false`.
+    pub frontmatter: bool,
+    /// The markdown constructs to enable while parsing, e.g.
+    /// `Constructs::gfm()` for tables, strikethrough and footnotes. Defaults to
+    /// `Constructs::default()`, i.e. plain CommonMark.
+    pub constructs: Constructs,
+    /// Whether to fail with [`crate::ParseError::DuplicateName`] when two test
+    /// cases share the same headings and name. Defaults to `false`.
+    pub detect_duplicates: bool,
+    /// Whether to treat each item of a top-level list as an arg, in document
+    /// order alongside code blocks. Ordered and unordered lists behave the same.
+    /// Nested lists are ignored: only an item's own text becomes the arg.
+    /// Defaults to `false`.
+    pub collect_list_items: bool,
+    /// Whether to treat GFM tables as args, available through [`crate::Arg::table`].
+    /// Enables the `gfm_table` construct. Defaults to `false`.
+    pub collect_tables: bool,
+    /// Whether to emit a test case with no args for each leaf heading that has
+    /// no code blocks of its own. Headings which only contain child headings
+    /// don't produce a test case. Defaults to `false`.
+    pub include_empty: bool,
+    /// How whitespace is trimmed from the value of each arg. Defaults to
+    /// `ArgTrim::None`, leaving values as the markdown parser produced them.
+    pub trim_args: ArgTrim,
+    /// The name given to test cases before the first heading. Defaults to
+    /// `"(Unnamed test)"`.
+    pub unnamed_name: String,
+    /// Whether a thematic break (`---`) ends the current test case, so that one
+    /// heading can hold several. The test cases share the heading's name and
+    /// `line_number`, so tell them apart by their `span` or the lines of their args.
+    /// They aren't duplicates of each other as far as [`Config::detect_duplicates`]
+    /// is concerned. Defaults to `false`.
+    pub split_on_thematic_break: bool,
+    /// Whether to ignore indented code blocks, so that only fenced code blocks
+    /// become args. Defaults to `false`, treating indented code blocks like fenced
+    /// ones with no language.
+    pub ignore_indented_code: bool,
+    /// Whether a code block whose language is the options keyword, e.g.
+    /// ```` ```options ````, is also treated as options. The meta is checked first.
+    /// Defaults to `false`.
+    pub options_by_lang: bool,
+    /// Headings deeper than this are ignored, as if they were prose: they neither
+    /// start a new section nor end the current test case. Defaults to `None`,
+    /// tracking headings of every depth.
+    pub max_heading_depth: Option<u8>,
+    /// Whether to fail with [`crate::ParseError::UnknownMeta`] when the meta of a
+    /// code block (the part after the language) has a token with no meaning to
+    /// this crate, e.g. the misspelled `optins` in ```` ```toml optins ````. The
+    /// language itself is never checked. Defaults to `false`, treating such blocks
+    /// as args.
+    pub strict: bool,
+    /// Whether each section without an enclosing heading starts from the root
+    /// options passed in, plus any frontmatter, rather than from the options in
+    /// effect before it. Options blocks before the first heading then only apply
+    /// to test cases before the first heading. Either way, a section starts from
+    /// its parent's options when it begins, not from those of its previous
+    /// sibling, and nested sections inherit from their parent. Defaults to
+    /// `false`.
+    pub scope_options_to_section: bool,
+    /// A line which splits a code block into several args, e.g. `---`. Each arg
+    /// keeps the language and meta of the code block, and its `line` is that of
+    /// the delimiter before it. Defaults to `None`, keeping each code block whole.
+    pub intra_block_delimiter: Option<String>,
+    /// Whether to match the options keyword case-insensitively, so that
+    /// `Options` and `OPTIONS` also mark options blocks. Defaults to `false`.
+    pub options_keyword_case_insensitive: bool,
+    /// A pattern to match meta tokens against instead of `options_keyword`,
+    /// taking precedence over `options_keyword_case_insensitive` too. A token
+    /// matches if any part of it does, so anchor the pattern, as in
+    /// `^opt(ions)?$`. Defaults to `None`.
+    #[cfg(feature = "regex")]
+    pub options_keyword_pattern: Option<KeywordPattern>,
+    /// Whether an options block which fails to merge is reported as a
+    /// [`crate::Warning::OptionsMerge`] rather than an error. The section keeps the
+    /// options it had before the block. Defaults to `false`.
+    pub continue_on_error: bool,
+    /// Whether the args of a test case continue under headings nested within its
+    /// section, e.g. an input under `## Parse` followed by its expected output under
+    /// `### Expected`. Once a section has args, any deeper heading is treated as
+    /// prose rather than starting a new test case, so its args, options blocks and
+    /// comments all belong to the enclosing test case. The test case ends at the
+    /// next heading at its own depth or shallower. Sections without args of their
+    /// own nest as usual, as do args before the first heading. Defaults to `false`.
+    pub merge_child_args: bool,
+    /// The language recorded for arg code blocks without one of their own, e.g.
+    /// `sql` so that a bare ```` ``` ```` block has `lang` set to `sql`. Defaults to
+    /// `None`, leaving `lang` empty.
+    pub default_arg_lang: Option<String>,
+    /// Whether to replace `\r\n` line endings with `\n` in arg values, so that
+    /// files authored on Windows produce the same args as any other. Line numbers
+    /// are unaffected either way, as the markdown parser counts `\r\n` as a single
+    /// line ending. Defaults to `true`.
+    pub normalize_newlines: bool,
+    /// Whether options blocks apply to every test case in their section, even
+    /// those which come before the block. This only makes a difference when a
+    /// section holds several test cases, as with
+    /// [`Config::split_on_thematic_break`]: each is then held back until the section
+    /// ends, so that it gets the section's final options. Defaults to `false`, where
+    /// a block applies to the test case it's part of and those after it.
+    pub options_apply_to_section: bool,
+    /// Decides which nodes end the current test case, e.g. only headings up to
+    /// level 2. Applied after [`Config::max_heading_depth`]. Defaults to `None`, using
+    /// [`crate::HeadingBoundary`].
+    pub boundary: Option<SharedBoundary>,
+    /// Whether args from code blocks with a `title="name"` token in their meta go
+    /// into [`crate::TestCase::named_args`], keyed by the title, rather than `args`.
+    /// Code blocks without a title stay in `args`. When two code blocks in a test
+    /// case share a title, the later one wins. Defaults to `false`.
+    pub args_as_map: bool,
+    /// Whether to look for code blocks inside lists and block quotes, e.g. a fenced
+    /// code block under each step of a numbered list, rather than only at the top
+    /// level of the document. Only the code blocks are taken from them, in document
+    /// order, becoming args or options as usual; headings, paragraphs and other content
+    /// inside lists and block quotes are ignored. Lists are still collected as a whole
+    /// with [`Config::collect_list_items`]. Defaults to `false`.
+    pub deep_scan: bool,
+    /// The number of args a test case may have before a [`crate::Warning::TooManyArgs`]
+    /// is reported, catching documents where a heading was left out and many test cases
+    /// run together. The test case is still emitted, with all of its args unless
+    /// [`Config::truncate_args`] is enabled. Defaults to `None`, allowing any number.
+    pub max_args_per_case: Option<usize>,
+    /// Whether to drop the args of a test case beyond [`Config::max_args_per_case`],
+    /// keeping the first ones. Defaults to `false`.
+    pub truncate_args: bool,
+    /// Whether an options block may merge the contents of a file named by a
+    /// `file="path"` token in its meta. The path is relative to the directory of the
+    /// markdown file being parsed, and must stay within it: absolute paths and paths
+    /// leading out of it through `..` are a [`crate::ParseError::OptionsFile`], as is
+    /// any `file=` token in markdown which wasn't read from a path. Defaults to
+    /// `false`, ignoring `file=` tokens so that documents can't read arbitrary files.
+    pub options_files: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            options_keyword: "options".to_string(),
+            frontmatter: false,
+            constructs: Constructs::default(),
+            detect_duplicates: false,
+            collect_list_items: false,
+            collect_tables: false,
+            include_empty: false,
+            trim_args: ArgTrim::None,
+            unnamed_name: "(Unnamed test)".to_string(),
+            split_on_thematic_break: false,
+            ignore_indented_code: false,
+            options_by_lang: false,
+            max_heading_depth: None,
+            strict: false,
+            scope_options_to_section: false,
+            intra_block_delimiter: None,
+            options_keyword_case_insensitive: false,
+            #[cfg(feature = "regex")]
+            options_keyword_pattern: None,
+            continue_on_error: false,
+            merge_child_args: false,
+            default_arg_lang: None,
+            normalize_newlines: true,
+            options_apply_to_section: false,
+            boundary: None,
+            args_as_map: false,
+            deep_scan: false,
+            max_args_per_case: None,
+            truncate_args: false,
+            options_files: false,
+        }
+    }
+}
+
+/// See [`Config::trim_args`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ArgTrim {
+    /// Leave arg values as they are.
+    #[default]
+    None,
+    /// Strip a single trailing `\n`, if there is one.
+    TrailingNewline,
+    /// Strip all leading and trailing whitespace.
+    Full,
+}
+
+impl ArgTrim {
+    pub fn apply(self, value: String) -> String {
+        match self {
+            ArgTrim::None => value,
+            ArgTrim::TrailingNewline => match value.strip_suffix('\n') {
+                Some(trimmed) => trimmed.to_string(),
+                None => value,
+            },
+            ArgTrim::Full => value.trim().to_string(),
+        }
+    }
+}
+
+/// A [`regex::Regex`] for [`Config::options_keyword_pattern`]. Patterns are
+/// compared by their source.
+#[cfg(feature = "regex")]
+#[derive(Debug, Clone)]
+pub struct KeywordPattern(pub regex::Regex);
+
+#[cfg(feature = "regex")]
+impl PartialEq for KeywordPattern {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.as_str() == other.0.as_str()
+    }
+}
+
+#[cfg(feature = "regex")]
+impl Eq for KeywordPattern {}
+
+impl Config {
+    /// Whether `token` is the options keyword, by the first of
+    /// `options_keyword_pattern`, `options_keyword_case_insensitive` or an
+    /// exact match which applies.
+    pub fn is_options_keyword(&self, token: &str) -> bool {
+        #[cfg(feature = "regex")]
+        if let Some(pattern) = &self.options_keyword_pattern {
+            return pattern.0.is_match(token);
+        }
+        if self.options_keyword_case_insensitive {
+            token.eq_ignore_ascii_case(&self.options_keyword)
+        } else {
+            token == self.options_keyword
+        }
+    }
+
+    /// Whether `node` ends the current test case, according to `boundary`.
+    pub fn is_boundary(&self, node: &Node) -> bool {
+        match &self.boundary {
+            Some(boundary) => boundary.0.is_boundary(node),
+            None => HeadingBoundary.is_boundary(node),
+        }
+    }
+
+    pub fn parse_options(&self) -> ParseOptions {
+        ParseOptions {
+            constructs: Constructs {
+                frontmatter: self.frontmatter || self.constructs.frontmatter,
+                gfm_table: self.collect_tables || self.constructs.gfm_table,
+                ..self.constructs.clone()
+            },
+            ..ParseOptions::default()
+        }
+    }
+}
@@ -0,0 +1,72 @@
+use crate::{text::fence, SerializeOptions, TestCase, TryMergeSerialized};
+
+/// Renders test cases back into markdown, such that parsing the result with
+/// `Options::default()` as the root options and the default [`crate::Config`]
+/// yields test cases which are [semantically equal](TestCase::semantic_eq).
+/// Useful for canonicalizing a hand-written spec file.
+///
+/// Headings are emitted from each test case's `headings` and `name`, nesting
+/// one level deeper per heading. A test case gets an options block only when
+/// its options differ from those it would inherit. Test cases with a `depth`
+/// of 0 are emitted before any heading.
+pub fn to_markdown<Options>(cases: &[TestCase<Options>]) -> String
+where
+    Options: TryMergeSerialized + SerializeOptions + Clone + Default,
+{
+    let mut blocks: Vec<String> = vec![];
+    let mut root = Options::default();
+    let mut emitted: Vec<String> = vec![];
+    // The path and options of each emitted section with test cases, which
+    // sections nested under it inherit.
+    let mut scopes: Vec<(Vec<String>, Options)> = vec![];
+    for case in cases {
+        let base = if case.depth > 0 {
+            scopes.retain(|(path, _)| case.headings.starts_with(path));
+            let common = emitted
+                .iter()
+                .zip(&case.headings)
+                .take_while(|(a, b)| a == b)
+                .count();
+            for (index, heading) in case.headings.iter().enumerate().skip(common) {
+                blocks.push(format!("{} {}", "#".repeat(index + 1), heading));
+            }
+            let hashes = "#".repeat(case.headings.len() + 1);
+            blocks.push(if case.title.is_empty() || case.title == case.name {
+                format!("{} {}", hashes, case.name)
+            } else {
+                format!("{} {} <!-- name: {} -->", hashes, case.title, case.name)
+            });
+            emitted = case.headings.iter().chain([&case.name]).cloned().collect();
+            match scopes.last() {
+                Some((_, options)) => options,
+                None => &root,
+            }
+        } else {
+            &root
+        };
+        let options_block = case.options.serialize_options(base);
+        if let Some(description) = &case.description {
+            blocks.push(description.clone());
+        }
+        if let Some(source) = options_block {
+            blocks.push(fence(Some(Options::LANG), Some("options"), &source));
+        }
+        for arg in &case.args {
+            if let Some(label) = &arg.label {
+                blocks.push(format!("<!-- arg: {} -->", label));
+            }
+            blocks.push(match arg.table {
+                Some(_) => arg.value.clone(),
+                None => fence(arg.lang.as_deref(), arg.meta.as_deref(), &arg.value),
+            });
+        }
+        if case.depth > 0 {
+            scopes.push((emitted.clone(), case.options.clone()));
+        } else {
+            root = case.options.clone();
+        }
+    }
+    let mut markdown = blocks.join("\n\n");
+    markdown.push('\n');
+    markdown
+}
@@ -0,0 +1,122 @@
+use std::{fmt, path::PathBuf};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseError {
+    /// The markdown parser itself rejected the document.
+    Markdown(String),
+    /// A heading contained something other than plain text.
+    NonTextHeading { line: usize },
+    /// An options code block could not be merged onto the inherited options.
+    OptionsMerge {
+        line: usize,
+        /// The line of the closing fence, or `line` when the end of the block
+        /// isn't known.
+        end_line: usize,
+        /// The raw content of the options code block.
+        source: String,
+        /// The error returned by [`crate::TryMergeSerialized::try_merge_serialized`].
+        message: String,
+    },
+    /// A node which needs a position to be reported on didn't have one.
+    MissingPosition,
+    /// Two test cases have the same headings and name.
+    DuplicateName {
+        /// The headings and name of the test cases, joined with `" > "`.
+        name: String,
+        first_line: usize,
+        second_line: usize,
+    },
+    /// One of the layers passed to [`crate::get_test_cases_layered`] could not
+    /// be merged onto the options before it.
+    OptionsLayer {
+        /// The position of the layer, starting from 0.
+        index: usize,
+        message: String,
+    },
+    /// No section has the headings passed to [`crate::options_at_path`].
+    UnknownPath {
+        /// The headings, joined with `" > "`.
+        path: String,
+    },
+    /// An options block named a file it may not read, per
+    /// [`crate::Config::options_files`].
+    OptionsFile { line: usize, path: String },
+    /// A markdown file couldn't be read.
+    Io { path: PathBuf, message: String },
+    /// A reader passed to [`crate::get_test_cases_from_reader`] failed.
+    Read { message: String },
+    /// The content of a reader wasn't valid UTF-8, from the given byte offset.
+    Encoding { offset: usize },
+    /// A code block's meta had an unrecognized token, with
+    /// [`crate::Config::strict`] enabled.
+    UnknownMeta { line: usize, meta: String },
+    /// An error occurred while parsing the given markdown file.
+    InFile {
+        path: PathBuf,
+        error: Box<ParseError>,
+    },
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::Markdown(message) => write!(f, "Failed to parse markdown: {}", message),
+            ParseError::NonTextHeading { line } => write!(
+                f,
+                "Markdown headings must contain plain text (heading at line {})",
+                line
+            ),
+            ParseError::OptionsMerge {
+                line,
+                source,
+                message,
+                ..
+            } => write!(
+                f,
+                "Failed to parse options from code block at line {}: {}\n{}",
+                line, message, source
+            ),
+            ParseError::OptionsLayer { index, message } => {
+                write!(f, "Failed to merge options layer {}: {}", index, message)
+            }
+            ParseError::UnknownPath { path } => write!(f, "No section \"{}\"", path),
+            ParseError::MissingPosition => write!(f, "Markdown node has no position"),
+            ParseError::DuplicateName {
+                name,
+                first_line,
+                second_line,
+            } => write!(
+                f,
+                "Duplicate test case \"{}\" at lines {} and {}",
+                name, first_line, second_line
+            ),
+            ParseError::OptionsFile { line, path } => write!(
+                f,
+                "Options file \"{}\" at line {} must be a relative path within the markdown file's directory",
+                path, line
+            ),
+            ParseError::Io { path, message } => {
+                write!(f, "Failed to read {}: {}", path.display(), message)
+            }
+            ParseError::Read { message } => write!(f, "Failed to read markdown: {}", message),
+            ParseError::Encoding { offset } => {
+                write!(f, "Markdown is not valid UTF-8 (at byte {})", offset)
+            }
+            ParseError::UnknownMeta { line, meta } => write!(
+                f,
+                "Unrecognized meta \"{}\" on code block at line {}",
+                meta, line
+            ),
+            ParseError::InFile { path, error } => write!(f, "{}: {}", path.display(), error),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ParseError::InFile { error, .. } => Some(error.as_ref()),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,301 @@
+use crate::{
+    files::{markdown_files, read_file, read_reader},
+    ArgTrim, Boundary, Config, Constructs, ParseError, SharedBoundary, TestCase, TestCaseIter,
+    TryMergeSerialized, Warning,
+};
+use markdown::mdast::Node;
+use std::{io::Read, path::Path, sync::Arc};
+
+/// Builder for extracting test cases with a non-default [`Config`].
+///
+/// ```ignore
+/// let test_cases = TestCaseExtractor::new(Options::default())
+///     .options_keyword("opts")
+///     .enable_frontmatter(true)
+///     .parse(content)?;
+/// ```
+///
+/// The extractor can be cloned or reused to parse several files with the same
+/// configuration.
+#[derive(Debug, Clone)]
+pub struct TestCaseExtractor<Options: TryMergeSerialized + Clone + Default> {
+    root_options: Options,
+    config: Config,
+}
+
+impl<Options: TryMergeSerialized + Clone + Default> TestCaseExtractor<Options> {
+    pub fn new(root_options: Options) -> Self {
+        Self {
+            root_options,
+            config: Config::default(),
+        }
+    }
+
+    /// Replaces the whole configuration at once.
+    pub fn config(mut self, config: Config) -> Self {
+        self.config = config;
+        self
+    }
+
+    pub fn options_keyword(mut self, keyword: impl Into<String>) -> Self {
+        self.config.options_keyword = keyword.into();
+        self
+    }
+
+    pub fn enable_frontmatter(mut self, enable: bool) -> Self {
+        self.config.frontmatter = enable;
+        self
+    }
+
+    pub fn constructs(mut self, constructs: Constructs) -> Self {
+        self.config.constructs = constructs;
+        self
+    }
+
+    pub fn detect_duplicates(mut self, enable: bool) -> Self {
+        self.config.detect_duplicates = enable;
+        self
+    }
+
+    pub fn collect_list_items(mut self, enable: bool) -> Self {
+        self.config.collect_list_items = enable;
+        self
+    }
+
+    pub fn collect_tables(mut self, enable: bool) -> Self {
+        self.config.collect_tables = enable;
+        self
+    }
+
+    pub fn include_empty(mut self, enable: bool) -> Self {
+        self.config.include_empty = enable;
+        self
+    }
+
+    pub fn trim_args(mut self, trim: ArgTrim) -> Self {
+        self.config.trim_args = trim;
+        self
+    }
+
+    pub fn unnamed_name(mut self, name: impl Into<String>) -> Self {
+        self.config.unnamed_name = name.into();
+        self
+    }
+
+    pub fn split_on_thematic_break(mut self, enable: bool) -> Self {
+        self.config.split_on_thematic_break = enable;
+        self
+    }
+
+    pub fn ignore_indented_code(mut self, enable: bool) -> Self {
+        self.config.ignore_indented_code = enable;
+        self
+    }
+
+    pub fn options_by_lang(mut self, enable: bool) -> Self {
+        self.config.options_by_lang = enable;
+        self
+    }
+
+    pub fn max_heading_depth(mut self, depth: u8) -> Self {
+        self.config.max_heading_depth = Some(depth);
+        self
+    }
+
+    pub fn strict(mut self, enable: bool) -> Self {
+        self.config.strict = enable;
+        self
+    }
+
+    pub fn scope_options_to_section(mut self, enable: bool) -> Self {
+        self.config.scope_options_to_section = enable;
+        self
+    }
+
+    pub fn intra_block_delimiter(mut self, delimiter: impl Into<String>) -> Self {
+        self.config.intra_block_delimiter = Some(delimiter.into());
+        self
+    }
+
+    pub fn options_keyword_case_insensitive(mut self, enable: bool) -> Self {
+        self.config.options_keyword_case_insensitive = enable;
+        self
+    }
+
+    #[cfg(feature = "regex")]
+    pub fn options_keyword_pattern(mut self, pattern: regex::Regex) -> Self {
+        self.config.options_keyword_pattern = Some(crate::KeywordPattern(pattern));
+        self
+    }
+
+    pub fn continue_on_error(mut self, enable: bool) -> Self {
+        self.config.continue_on_error = enable;
+        self
+    }
+
+    pub fn merge_child_args(mut self, enable: bool) -> Self {
+        self.config.merge_child_args = enable;
+        self
+    }
+
+    pub fn default_arg_lang(mut self, lang: impl Into<String>) -> Self {
+        self.config.default_arg_lang = Some(lang.into());
+        self
+    }
+
+    pub fn normalize_newlines(mut self, enable: bool) -> Self {
+        self.config.normalize_newlines = enable;
+        self
+    }
+
+    pub fn options_apply_to_section(mut self, enable: bool) -> Self {
+        self.config.options_apply_to_section = enable;
+        self
+    }
+
+    pub fn boundary(mut self, boundary: impl Boundary + Send + Sync + 'static) -> Self {
+        self.config.boundary = Some(SharedBoundary(Arc::new(boundary)));
+        self
+    }
+
+    pub fn args_as_map(mut self, enable: bool) -> Self {
+        self.config.args_as_map = enable;
+        self
+    }
+
+    pub fn deep_scan(mut self, enable: bool) -> Self {
+        self.config.deep_scan = enable;
+        self
+    }
+
+    pub fn max_args_per_case(mut self, max: usize) -> Self {
+        self.config.max_args_per_case = Some(max);
+        self
+    }
+
+    pub fn truncate_args(mut self, enable: bool) -> Self {
+        self.config.truncate_args = enable;
+        self
+    }
+
+    pub fn options_files(mut self, enable: bool) -> Self {
+        self.config.options_files = enable;
+        self
+    }
+
+    pub fn parse(&self, content: String) -> Result<Vec<TestCase<Options>>, ParseError> {
+        self.iter(content).collect()
+    }
+
+    /// Like [`TestCaseExtractor::parse`], but also returns any [`Warning`]s
+    /// about the document, ordered by line.
+    pub fn parse_with_warnings(
+        &self,
+        content: String,
+    ) -> Result<(Vec<TestCase<Options>>, Vec<Warning>), ParseError> {
+        let mut iter = TestCaseIter::new(content, self.root_options.clone(), self.config.clone());
+        let test_cases = iter.by_ref().collect::<Result<_, _>>()?;
+        Ok((test_cases, iter.take_warnings()))
+    }
+
+    /// Extracts test cases from an already parsed [`Node::Root`]. See
+    /// [`crate::get_test_cases_from_ast`].
+    pub fn parse_ast(&self, root: Node) -> Result<Vec<TestCase<Options>>, ParseError> {
+        TestCaseIter::from_root(
+            Ok(root),
+            String::new(),
+            self.root_options.clone(),
+            self.config.clone(),
+        )
+        .collect()
+    }
+
+    /// Reads markdown from `reader` and extracts its test cases. The content
+    /// must be UTF-8.
+    pub fn parse_reader<R: Read>(&self, reader: R) -> Result<Vec<TestCase<Options>>, ParseError> {
+        self.parse(read_reader(reader)?)
+    }
+
+    /// Reads a markdown file and extracts its test cases.
+    pub fn parse_path<P: AsRef<Path>>(
+        &self,
+        path: P,
+    ) -> Result<Vec<TestCase<Options>>, ParseError> {
+        let path = path.as_ref();
+        let content = read_file(path)?;
+        let mut iter = TestCaseIter::new(content, self.root_options.clone(), self.config.clone());
+        iter.source = Some(path.to_path_buf());
+        iter.collect::<Result<_, _>>()
+            .map_err(|error| ParseError::InFile {
+                path: path.to_path_buf(),
+                error: Box::new(error),
+            })
+    }
+
+    /// Recursively finds all `.md` files within a directory and extracts their
+    /// test cases, processing files in sorted path order.
+    pub fn parse_dir<P: AsRef<Path>>(&self, dir: P) -> Result<Vec<TestCase<Options>>, ParseError> {
+        let mut test_cases = vec![];
+        for path in markdown_files(dir.as_ref())? {
+            test_cases.extend(self.parse_path(path)?);
+        }
+        Ok(test_cases)
+    }
+
+    /// Like [`TestCaseExtractor::parse_dir`], but parses files concurrently.
+    /// The test cases are still returned in sorted path order, and the error
+    /// from the first failing file in that order is the one reported.
+    #[cfg(feature = "parallel")]
+    pub fn par_parse_dir<P: AsRef<Path>>(
+        &self,
+        dir: P,
+    ) -> Result<Vec<TestCase<Options>>, ParseError>
+    where
+        Options: Send + Sync,
+    {
+        use rayon::prelude::*;
+
+        let results = markdown_files(dir.as_ref())?
+            .par_iter()
+            .map(|path| self.parse_path(path))
+            .collect::<Vec<_>>();
+        let mut test_cases = vec![];
+        for result in results {
+            test_cases.extend(result?);
+        }
+        Ok(test_cases)
+    }
+
+    /// Calls `f` with each test case as soon as it's complete, stopping at the
+    /// first error.
+    pub fn visit<F: FnMut(TestCase<Options>)>(
+        &self,
+        content: String,
+        mut f: F,
+    ) -> Result<(), ParseError> {
+        for test_case in self.iter(content) {
+            f(test_case?);
+        }
+        Ok(())
+    }
+
+    /// Counts the test cases in `content`, failing on the same errors as
+    /// [`TestCaseExtractor::parse`].
+    pub fn validate(&self, content: String) -> Result<usize, ParseError> {
+        self.iter(content)
+            .try_fold(0, |count, test_case| test_case.map(|_| count + 1))
+    }
+
+    /// The options in effect for test cases directly under the section with
+    /// the headings `path`. See [`crate::options_at_path`].
+    pub fn options_at_path(&self, content: String, path: &[&str]) -> Result<Options, ParseError> {
+        TestCaseIter::new(content, self.root_options.clone(), self.config.clone()).options_at(path)
+    }
+
+    pub fn iter(
+        &self,
+        content: String,
+    ) -> impl Iterator<Item = Result<TestCase<Options>, ParseError>> {
+        TestCaseIter::new(content, self.root_options.clone(), self.config.clone())
+    }
+}
@@ -0,0 +1,184 @@
+use crate::{ParseError, TestCase, TestCaseExtractor, TryMergeSerialized};
+use std::{
+    io::Read,
+    path::{Component, Path, PathBuf},
+};
+
+/// Reads a markdown file and extracts its test cases.
+pub fn test_cases_from_path<P: AsRef<Path>, Options: TryMergeSerialized + Clone + Default>(
+    path: P,
+    root_options: Options,
+) -> Result<Vec<TestCase<Options>>, ParseError> {
+    TestCaseExtractor::new(root_options).parse_path(path)
+}
+
+/// Reads markdown from `reader`, such as stdin, and extracts its test cases.
+/// The content must be UTF-8.
+pub fn get_test_cases_from_reader<R: Read, Options: TryMergeSerialized + Clone + Default>(
+    reader: R,
+    root_options: Options,
+) -> Result<Vec<TestCase<Options>>, ParseError> {
+    TestCaseExtractor::new(root_options).parse_reader(reader)
+}
+
+/// Recursively finds all `.md` files within a directory and extracts their
+/// test cases, processing files in sorted path order.
+pub fn test_cases_from_dir<P: AsRef<Path>, Options: TryMergeSerialized + Clone + Default>(
+    dir: P,
+    root_options: Options,
+) -> Result<Vec<TestCase<Options>>, ParseError> {
+    TestCaseExtractor::new(root_options).parse_dir(dir)
+}
+
+/// Like [`test_cases_from_dir`], but parses files concurrently with rayon.
+#[cfg(feature = "parallel")]
+pub fn par_test_cases_from_dir<
+    P: AsRef<Path>,
+    Options: TryMergeSerialized + Clone + Default + Send + Sync,
+>(
+    dir: P,
+    root_options: Options,
+) -> Result<Vec<TestCase<Options>>, ParseError> {
+    TestCaseExtractor::new(root_options).par_parse_dir(dir)
+}
+
+/// Whether a relative `path` stays within the directory it's relative to, even
+/// if it passes through `..` on the way.
+pub(crate) fn is_contained(path: &Path) -> bool {
+    let mut depth = 0usize;
+    path.components().all(|component| match component {
+        Component::Normal(_) => {
+            depth += 1;
+            true
+        }
+        Component::CurDir => true,
+        Component::ParentDir => depth.checked_sub(1).map(|d| depth = d).is_some(),
+        Component::RootDir | Component::Prefix(_) => false,
+    })
+}
+
+pub(crate) fn read_file(path: &Path) -> Result<String, ParseError> {
+    std::fs::read_to_string(path).map_err(|e| io_error(path, e))
+}
+
+pub(crate) fn read_reader<R: Read>(mut reader: R) -> Result<String, ParseError> {
+    let mut bytes = vec![];
+    reader
+        .read_to_end(&mut bytes)
+        .map_err(|e| ParseError::Read {
+            message: e.to_string(),
+        })?;
+    String::from_utf8(bytes).map_err(|e| ParseError::Encoding {
+        offset: e.utf8_error().valid_up_to(),
+    })
+}
+
+/// Lists the markdown files within a directory and its subdirectories, sorted
+/// by path.
+pub(crate) fn markdown_files(dir: &Path) -> Result<Vec<PathBuf>, ParseError> {
+    let mut files = vec![];
+    collect_markdown_files(dir, &mut files)?;
+    files.sort();
+    Ok(files)
+}
+
+fn collect_markdown_files(dir: &Path, files: &mut Vec<PathBuf>) -> Result<(), ParseError> {
+    for entry in std::fs::read_dir(dir).map_err(|e| io_error(dir, e))? {
+        let path = entry.map_err(|e| io_error(dir, e))?.path();
+        if path.is_dir() {
+            collect_markdown_files(&path, files)?;
+        } else if path.extension().is_some_and(|ext| ext == "md") {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+fn io_error(path: &Path, error: std::io::Error) -> ParseError {
+    ParseError::Io {
+        path: path.to_path_buf(),
+        message: error.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{get_test_cases_from_reader, test_cases_from_dir};
+    use crate::ParseError;
+    use std::{fs, path::PathBuf};
+
+    fn write_files(name: &str, files: &[(&str, &str)]) -> PathBuf {
+        let dir = std::env::temp_dir().join(name);
+        let _ = fs::remove_dir_all(&dir);
+        for (path, content) in files {
+            let path = dir.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        }
+        dir
+    }
+
+    #[test]
+    fn test_from_dir() {
+        let dir = write_files(
+            "testcase-markdown-from-dir",
+            &[
+                ("b.md", "# B\n\n```\nb\n```\n"),
+                ("a/z.md", "# Z\n\n```\nz\n```\n"),
+                ("a.md", "# A\n\n```\na\n```\n"),
+                ("notes.txt", "# Ignored\n\n```\nx\n```\n"),
+            ],
+        );
+        let result = test_cases_from_dir(&dir, ()).unwrap();
+        let names = result.iter().map(|c| c.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(names, vec!["Z", "A", "B"]);
+        assert_eq!(result[0].source, Some(dir.join("a/z.md")));
+    }
+
+    #[test]
+    fn test_from_dir_error() {
+        let dir = write_files(
+            "testcase-markdown-from-dir-error",
+            &[
+                ("ok.md", "# A\n\n```\na\n```\n"),
+                ("sub/bad.md", "# ![x](y)\n"),
+            ],
+        );
+        let result = test_cases_from_dir(&dir, ());
+        let Err(ParseError::InFile { path, error }) = result else {
+            panic!("Expected an error naming the file");
+        };
+        assert_eq!(path, dir.join("sub/bad.md"));
+        assert_eq!(*error, ParseError::NonTextHeading { line: 1 });
+    }
+
+    #[test]
+    fn test_from_reader() {
+        let content = "# A\n\n```\na\n```\n";
+        let result = get_test_cases_from_reader(content.as_bytes(), ()).unwrap();
+        assert_eq!(result[0].name, "A");
+        let result = get_test_cases_from_reader(&b"# A\n\xff"[..], ());
+        assert_eq!(result, Err(ParseError::Encoding { offset: 4 }));
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn test_par_from_dir() {
+        let files = (0..50)
+            .map(|i| {
+                let path = format!("{}/{i}.md", i % 7);
+                let content = format!("# File {i}\n\n```\n{i}\n```\n\n## Sub\n\n```\nsub\n```\n");
+                (path, content)
+            })
+            .collect::<Vec<_>>();
+        let files = files
+            .iter()
+            .map(|(path, content)| (path.as_str(), content.as_str()))
+            .collect::<Vec<_>>();
+        let dir = write_files("testcase-markdown-par-from-dir", &files);
+        let sequential = test_cases_from_dir(&dir, ()).unwrap();
+        let parallel = super::par_test_cases_from_dir(&dir, ()).unwrap();
+        assert_eq!(sequential.len(), 100);
+        assert_eq!(parallel, sequential);
+    }
+}
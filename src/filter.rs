@@ -0,0 +1,246 @@
+use crate::{TestCase, TryMergeSerialized, Warning};
+
+impl<Options: TryMergeSerialized> TestCase<Options> {
+    /// Whether `query` is a prefix of the test case's headings followed by its
+    /// name. An empty query matches every test case.
+    pub fn matches_path(&self, query: &[&str]) -> bool {
+        self.matches_path_by(query, |a, b| a == b)
+    }
+
+    /// Like [`TestCase::matches_path`], but compares headings
+    /// case-insensitively.
+    pub fn matches_path_ignore_case(&self, query: &[&str]) -> bool {
+        self.matches_path_by(query, |a, b| a.to_lowercase() == b.to_lowercase())
+    }
+
+    /// Whether the test case has the `include` tags, according to `policy`,
+    /// and none of the `exclude` tags. An empty `include` matches every test
+    /// case, while a non-empty one never matches a test case without tags.
+    pub fn matches_tags(&self, include: &[&str], exclude: &[&str], policy: TagMatch) -> bool {
+        let has_tag = |tag: &&str| self.tags.iter().any(|t| t == tag);
+        let included = include.is_empty()
+            || match policy {
+                TagMatch::Any => include.iter().any(has_tag),
+                TagMatch::All => include.iter().all(has_tag),
+            };
+        included && !exclude.iter().any(has_tag)
+    }
+
+    fn matches_path_by(&self, query: &[&str], eq: impl Fn(&str, &str) -> bool) -> bool {
+        let path = self.headings.iter().chain([&self.name]);
+        query.len() <= self.headings.len() + 1
+            && query.iter().zip(path).all(|(q, segment)| eq(q, segment))
+    }
+}
+
+/// Whether a test case must have any or all of the tags it's filtered by. See
+/// [`TestCase::matches_tags`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TagMatch {
+    /// Match test cases with at least one of the tags.
+    #[default]
+    Any,
+    /// Match test cases with every one of the tags.
+    All,
+}
+
+/// Keeps only the test cases whose path starts with `query`. See
+/// [`TestCase::matches_path`].
+pub fn filter_by_path<Options: TryMergeSerialized>(
+    cases: Vec<TestCase<Options>>,
+    query: &[&str],
+) -> Vec<TestCase<Options>> {
+    cases
+        .into_iter()
+        .filter(|c| c.matches_path(query))
+        .collect()
+}
+
+/// Like [`filter_by_path`], but compares headings case-insensitively.
+pub fn filter_by_path_ignore_case<Options: TryMergeSerialized>(
+    cases: Vec<TestCase<Options>>,
+    query: &[&str],
+) -> Vec<TestCase<Options>> {
+    cases
+        .into_iter()
+        .filter(|c| c.matches_path_ignore_case(query))
+        .collect()
+}
+
+/// Keeps only the test cases with the `include` tags, according to `policy`,
+/// and none of the `exclude` tags. See [`TestCase::matches_tags`].
+pub fn filter_by_tags<Options: TryMergeSerialized>(
+    cases: Vec<TestCase<Options>>,
+    include: &[&str],
+    exclude: &[&str],
+    policy: TagMatch,
+) -> Vec<TestCase<Options>> {
+    cases
+        .into_iter()
+        .filter(|c| c.matches_tags(include, exclude, policy))
+        .collect()
+}
+
+/// Removes each test case which is [semantically equal](TestCase::semantic_eq)
+/// to the one right before it, such as accidental duplicates in a generated
+/// spec, returning a [`Warning::DuplicateCase`] for each. Only adjacent test
+/// cases are compared, so identical test cases elsewhere in the list are kept.
+///
+/// This is a pass over the extracted test cases rather than a [`crate::Config`]
+/// flag because comparing options needs `Options: PartialEq`, which extraction
+/// doesn't otherwise require.
+pub fn dedupe_test_cases<Options: TryMergeSerialized + PartialEq>(
+    cases: Vec<TestCase<Options>>,
+) -> (Vec<TestCase<Options>>, Vec<Warning>) {
+    let mut kept: Vec<TestCase<Options>> = vec![];
+    let mut warnings = vec![];
+    for case in cases {
+        match kept.last() {
+            Some(previous) if previous.semantic_eq(&case) => {
+                warnings.push(Warning::DuplicateCase {
+                    name: case.full_name(),
+                    first_line: previous.line_number,
+                    second_line: case.line_number,
+                });
+            }
+            _ => kept.push(case),
+        }
+    }
+    (kept, warnings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        dedupe_test_cases, filter_by_path, filter_by_path_ignore_case, filter_by_tags, TagMatch,
+    };
+    use crate::{get_test_cases, TestCase, TestCaseExtractor, Warning};
+
+    fn test_case(headings: &[&str], name: &str) -> TestCase<()> {
+        TestCase {
+            headings: headings.iter().map(|h| h.to_string()).collect(),
+            name: name.to_owned(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_matches_path() {
+        let case = test_case(&["Tests", "Fruits"], "Apple");
+        assert!(case.matches_path(&[]));
+        assert!(case.matches_path(&["Tests"]));
+        assert!(case.matches_path(&["Tests", "Fruits", "Apple"]));
+        assert!(!case.matches_path(&["Tests", "Fruits", "Apple", "More"]));
+        assert!(!case.matches_path(&["Fruits"]));
+        assert!(!case.matches_path(&["tests"]));
+        assert!(case.matches_path_ignore_case(&["tests", "FRUITS"]));
+    }
+
+    #[test]
+    fn test_filter_by_path() {
+        let cases = vec![
+            test_case(&["Tests", "Fruits"], "Apple"),
+            test_case(&["Tests", "Vegetables"], "Potato"),
+        ];
+        let result = filter_by_path(cases.clone(), &["Tests", "Fruits"]);
+        assert_eq!(result, cases[..1]);
+        assert_eq!(filter_by_path(cases.clone(), &[]), cases);
+        let result = filter_by_path_ignore_case(cases.clone(), &["tests", "vegetables"]);
+        assert_eq!(result, cases[1..]);
+    }
+
+    fn tagged(name: &str, tags: &[&str]) -> TestCase<()> {
+        TestCase {
+            name: name.to_owned(),
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_filter_by_tags() {
+        let cases = vec![
+            tagged("None", &[]),
+            tagged("Slow", &["slow"]),
+            tagged("SlowNetwork", &["slow", "network"]),
+            tagged("SlowFlaky", &["slow", "flaky"]),
+        ];
+        let names = |include: &[&str], exclude: &[&str], policy: TagMatch| {
+            filter_by_tags(cases.clone(), include, exclude, policy)
+                .into_iter()
+                .map(|c| c.name)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(names(&[], &[], TagMatch::Any).len(), 4);
+        assert_eq!(names(&[], &["flaky"], TagMatch::Any).len(), 3);
+        assert_eq!(
+            names(&["network", "flaky"], &[], TagMatch::Any),
+            vec!["SlowNetwork", "SlowFlaky"]
+        );
+        assert_eq!(
+            names(&["slow", "network"], &[], TagMatch::All),
+            vec!["SlowNetwork"]
+        );
+        assert_eq!(
+            names(&["slow"], &["flaky"], TagMatch::All),
+            vec!["Slow", "SlowNetwork"]
+        );
+    }
+
+    #[test]
+    fn test_dedupe_test_cases() {
+        let content = [
+            "# A",
+            "```\na\n```",
+            "# A",
+            "```\na\n```",
+            "# A",
+            "```\na\n```",
+            "# B",
+            "```\na\n```",
+            "# A",
+            "```\na\n```",
+        ]
+        .join("\n\n");
+        let cases = get_test_cases(content, ()).unwrap();
+        let (result, warnings) = dedupe_test_cases(cases);
+        let lines = result.iter().map(|c| c.line_number).collect::<Vec<_>>();
+        assert_eq!(lines, vec![1, 19, 25]);
+        assert_eq!(
+            warnings,
+            vec![
+                Warning::DuplicateCase {
+                    name: "A".to_owned(),
+                    first_line: 1,
+                    second_line: 7,
+                },
+                Warning::DuplicateCase {
+                    name: "A".to_owned(),
+                    first_line: 1,
+                    second_line: 13,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_dedupe_keeps_named_args_and_setup() {
+        let content = [
+            "# A",
+            "```sql title=query\na\n```",
+            "# A",
+            "```sql title=expected\na\n```",
+            "# A",
+            "```setup\ns\n```",
+            "```sql title=expected\na\n```",
+        ]
+        .join("\n\n");
+        let cases = TestCaseExtractor::new(())
+            .args_as_map(true)
+            .parse(content)
+            .unwrap();
+        let (result, warnings) = dedupe_test_cases(cases);
+        assert_eq!(result.len(), 3);
+        assert!(warnings.is_empty());
+    }
+}
@@ -0,0 +1,159 @@
+use crate::{MergeSerialized, SerializeOptions};
+use serde_json::{Map, Value};
+
+/// Parses the options block as JSON and deep-merges it onto the inherited
+/// options.
+impl MergeSerialized for Value {
+    fn merge_serialized(&self, source: String) -> Result<Self, String> {
+        let patch = serde_json::from_str::<Value>(&source).map_err(|e| e.to_string())?;
+        let mut merged = self.clone();
+        merge(&mut merged, patch);
+        Ok(merged)
+    }
+}
+
+/// Merges objects key by key, recursing into nested objects. Any other value
+/// in `patch`, including arrays, replaces the value in `base`.
+fn merge(base: &mut Value, patch: Value) {
+    match (base, patch) {
+        (Value::Object(base), Value::Object(patch)) => {
+            for (key, value) in patch {
+                match base.get_mut(&key) {
+                    Some(existing) => merge(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, patch) => *base = patch,
+    }
+}
+
+/// Emits only the keys which differ from `base`, recursing into nested
+/// objects. Keys missing from `self` can't be expressed as a merge, so they're
+/// left out.
+impl SerializeOptions for Value {
+    const LANG: &'static str = "json";
+
+    fn serialize_options(&self, base: &Self) -> Option<String> {
+        diff(base, self).map(|patch| patch.to_string())
+    }
+}
+
+/// The smallest value which [`merge`] can apply to `base` to get `target`, or
+/// `None` if they're equal.
+fn diff(base: &Value, target: &Value) -> Option<Value> {
+    match (base, target) {
+        _ if base == target => None,
+        (Value::Object(base), Value::Object(target)) => {
+            let mut patch = Map::new();
+            for (key, value) in target {
+                let changed = match base.get(key) {
+                    Some(existing) => diff(existing, value),
+                    None => Some(value.clone()),
+                };
+                if let Some(changed) = changed {
+                    patch.insert(key.clone(), changed);
+                }
+            }
+            (!patch.is_empty()).then_some(Value::Object(patch))
+        }
+        _ => Some(target.clone()),
+    }
+}
+
+/// An entry point for callers which can't implement [`MergeSerialized`], such
+/// as JavaScript via WASM. Options are JSON objects, and the test cases are
+/// returned serialized as JSON.
+#[cfg(feature = "wasm")]
+pub fn get_test_cases_json(content: String, root_options_json: String) -> Result<String, String> {
+    let root_options =
+        serde_json::from_str::<Value>(&root_options_json).map_err(|e| e.to_string())?;
+    let test_cases = crate::get_test_cases(content, root_options).map_err(|e| e.to_string())?;
+    serde_json::to_string(&test_cases).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{MergeSerialized, SerializeOptions};
+    use serde_json::{json, Value};
+
+    fn merge(base: Value, source: Value) -> Value {
+        base.merge_serialized(source.to_string()).unwrap()
+    }
+
+    #[test]
+    fn test_nested_merge() {
+        let base = json!({ "a": 1, "b": { "c": 2, "d": { "e": 3 } } });
+        let merged = merge(base, json!({ "a": 4, "b": { "d": { "f": 5 } } }));
+        assert_eq!(
+            merged,
+            json!({ "a": 4, "b": { "c": 2, "d": { "e": 3, "f": 5 } } })
+        );
+    }
+
+    #[test]
+    fn test_array_replacement() {
+        let base = json!({ "list": [1, 2, 3] });
+        let merged = merge(base, json!({ "list": [4] }));
+        assert_eq!(merged, json!({ "list": [4] }));
+    }
+
+    #[test]
+    fn test_type_mismatch() {
+        let merged = merge(json!({ "a": 1 }), json!({ "a": { "b": 2 } }));
+        assert_eq!(merged, json!({ "a": { "b": 2 } }));
+        let merged = merge(json!({ "a": { "b": 2 } }), json!({ "a": 1 }));
+        assert_eq!(merged, json!({ "a": 1 }));
+        assert_eq!(merge(Value::Null, json!({ "a": 1 })), json!({ "a": 1 }));
+    }
+
+    #[test]
+    fn test_invalid_json() {
+        let result = json!({}).merge_serialized("{".to_owned());
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_serialize_options() {
+        let base = json!({ "a": 1, "b": { "c": 2, "d": [1] } });
+        let target = json!({ "a": 1, "b": { "c": 3, "d": [1] }, "e": null });
+        let source = target.serialize_options(&base).unwrap();
+        assert_eq!(
+            serde_json::from_str::<Value>(&source).unwrap(),
+            json!({ "b": { "c": 3 }, "e": null })
+        );
+        assert_eq!(
+            merge(base.clone(), json!({ "b": { "c": 3 }, "e": null })),
+            target
+        );
+        assert_eq!(base.serialize_options(&base), None);
+        assert_eq!(json!(1).serialize_options(&base), Some("1".to_owned()));
+    }
+
+    #[cfg(feature = "wasm")]
+    #[test]
+    fn test_get_test_cases_json() {
+        use super::get_test_cases_json;
+
+        let content = [
+            "# A",
+            "```json options\n{\"b\": {\"c\": 2}}\n```",
+            "```\na\n```",
+        ]
+        .join("\n\n");
+        let root_options = json!({ "a": 1, "b": { "d": 3 } }).to_string();
+        let result = get_test_cases_json(content, root_options).unwrap();
+        let result = serde_json::from_str::<Value>(&result).unwrap();
+        assert_eq!(result[0]["name"], "A");
+        assert_eq!(
+            result[0]["options"],
+            json!({ "a": 1, "b": { "c": 2, "d": 3 } })
+        );
+        assert_eq!(result[0]["args"][0]["value"], "a");
+
+        let result = get_test_cases_json("# A".to_owned(), "{".to_owned());
+        assert!(result.is_err());
+    }
+}
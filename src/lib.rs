@@ -1,168 +1,1706 @@
+use files::{is_contained, read_file};
 use markdown::{
-    mdast::{Heading, Node},
-    to_mdast, ParseOptions,
+    mdast::{Blockquote, Code, Heading, List, ListItem},
+    to_mdast, unist,
 };
-use std::mem::take;
+use slug::Slugger;
+use std::{
+    collections::{BTreeMap, HashMap, VecDeque},
+    fmt::Display,
+    mem::take,
+    ops::Range,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+use text::{
+    comment_text, inline_text, meta_tokens, meta_value, normalize_newlines, parse_directives,
+    parse_tags, split_on_delimiter, take_comments,
+};
+use text::{fence, render_html};
+
+mod boundary;
+mod config;
+mod emit;
+mod error;
+mod extractor;
+mod files;
+mod filter;
+#[cfg(feature = "json")]
+mod json;
+mod merge;
+mod run;
+mod slug;
+#[cfg(test)]
+mod test_support;
+mod text;
+#[cfg(feature = "toml")]
+mod toml_table;
+mod tree;
+mod warning;
 
+pub use boundary::{Boundary, HeadingBoundary, SharedBoundary, ThematicBreakBoundary};
+#[cfg(feature = "regex")]
+pub use config::KeywordPattern;
+pub use config::{ArgTrim, Config};
+pub use emit::to_markdown;
+pub use error::ParseError;
+pub use extractor::TestCaseExtractor;
+#[cfg(feature = "parallel")]
+pub use files::par_test_cases_from_dir;
+pub use files::{get_test_cases_from_reader, test_cases_from_dir, test_cases_from_path};
+pub use filter::{
+    dedupe_test_cases, filter_by_path, filter_by_path_ignore_case, filter_by_tags, TagMatch,
+};
+#[cfg(feature = "wasm")]
+pub use json::get_test_cases_json;
+pub use markdown::mdast::Node;
+pub use markdown::Constructs;
+pub use merge::{merge_test_cases, MergeStrategy};
+pub use run::{run_cases, CaseOutcome, CaseResult};
+pub use tree::{build_tree, summarize, TestTree};
+pub use warning::Warning;
+
+/// Options are parsed from code blocks tagged with `options`.
+///
+/// Each options block is merged onto the options in effect at that point, so
+/// several blocks within one section accumulate in document order. Options
+/// belong to the section as a whole: a block placed after some args still
+/// applies to the test case those args form.
+///
+/// A block tagged with `options reset` is merged onto `Options::default()`
+/// instead, discarding whatever the section inherited. Like any other options
+/// block it only affects its own section and the sections nested under it;
+/// following sibling sections still inherit from their parent as usual.
+///
+/// With [`Config::scope_options_to_section`], options blocks before the first
+/// heading aren't inherited by the sections after it either.
 pub trait MergeSerialized {
     fn merge_serialized(&self, source: String) -> Result<Self, String>
     where
         Self: Sized;
 }
 
-struct Section<Options: MergeSerialized> {
+/// Like [`MergeSerialized`], but with a structured error type. This is the
+/// trait options actually need to implement. Every [`MergeSerialized`] type
+/// implements it with `Error = String`, so implement it directly only when
+/// you want to return your own error, e.g. the error from your deserializer.
+/// The error is reported through [`ParseError::OptionsMerge`].
+pub trait TryMergeSerialized: Sized {
+    type Error: Display;
+
+    fn try_merge_serialized(&self, source: String) -> Result<Self, Self::Error>;
+}
+
+impl<T: MergeSerialized> TryMergeSerialized for T {
+    type Error = String;
+
+    fn try_merge_serialized(&self, source: String) -> Result<Self, Self::Error> {
+        self.merge_serialized(source)
+    }
+}
+
+/// The inverse of [`MergeSerialized`], used by [`to_markdown`] to emit options
+/// blocks.
+pub trait SerializeOptions {
+    /// The language given to emitted options blocks, e.g. `toml`.
+    const LANG: &'static str = "text";
+
+    /// Returns the source of an options block which, merged onto `base`, yields
+    /// `self`, or `None` if they're identical.
+    fn serialize_options(&self, base: &Self) -> Option<String>;
+}
+
+/// Merges the source of an options block onto `base`, exactly as an options
+/// block in a document would be. Useful for unit testing an implementation of
+/// [`MergeSerialized`] or [`TryMergeSerialized`] without any markdown. Errors
+/// are rendered with their `Display` impl, as in [`ParseError::OptionsMerge`].
+pub fn merge_options<Options: TryMergeSerialized>(
+    base: &Options,
+    source: String,
+) -> Result<Options, String> {
+    base.try_merge_serialized(source)
+        .map_err(|error| error.to_string())
+}
+
+/// The line and source of each options block merged into some options, in
+/// order. See [`TestCase::options_trace`].
+pub type OptionsTrace = Vec<(usize, String)>;
+
+struct Section<Options: TryMergeSerialized> {
     pub depth: u8,
     pub name: String,
+    /// The heading's own text, which differs from `name` when the heading has
+    /// a `<!-- name: ... -->` comment.
+    pub title: String,
+    pub slug: String,
     pub line: usize,
-    pub options: Options,
+    /// The position of the heading itself.
+    pub span: Span,
+    /// The byte offsets of the heading itself.
+    pub range: Range<usize>,
+    /// Shared with the parent section until an options block overrides it.
+    pub options: Arc<Options>,
+    /// The line and source of each options block which contributed to
+    /// `options`.
+    pub options_trace: OptionsTrace,
+    /// Whether an options block within this section has set `options`.
+    pub local_options: bool,
+    /// Setup blocks from this section and its ancestors.
+    pub setup: Vec<String>,
+    /// Tags from this section and its ancestors.
+    pub tags: Vec<String>,
+    /// The lines of options blocks in this section which haven't applied to a
+    /// test case yet.
+    pub unused_options: Vec<usize>,
+    pub skipped: bool,
+    pub focused: bool,
 }
 
-struct SectionStack<Options: MergeSerialized + Clone> {
-    root_options: Options,
+struct SectionStack<Options: TryMergeSerialized + Clone> {
+    root_options: Arc<Options>,
+    root_options_trace: OptionsTrace,
+    root_local_options: bool,
+    root_setup: Vec<String>,
+    root_tags: Vec<String>,
+    root_unused_options: Vec<usize>,
+    /// Unused options blocks from sections which have ended.
+    ended_unused_options: Vec<usize>,
     sections: Vec<Section<Options>>,
+    /// The options and trace each section without an enclosing heading starts
+    /// from, when options are scoped to sections.
+    scoped_base: Option<(Arc<Options>, OptionsTrace)>,
+    slugger: Slugger,
 }
 
-impl<Options: MergeSerialized + Clone> SectionStack<Options> {
-    pub fn new(root_options: Options) -> Self {
+impl<Options: TryMergeSerialized + Clone> SectionStack<Options> {
+    pub fn new(root_options: Options, scope_options: bool) -> Self {
+        let root_options = Arc::new(root_options);
         Self {
+            scoped_base: scope_options.then(|| (Arc::clone(&root_options), vec![])),
             root_options,
+            root_options_trace: vec![],
+            root_local_options: false,
+            root_setup: vec![],
+            root_tags: vec![],
+            root_unused_options: vec![],
+            ended_unused_options: vec![],
             sections: Vec::<Section<Options>>::new(),
+            slugger: Slugger::default(),
         }
     }
 
-    pub fn push_heading(&mut self, heading: Heading) {
-        let Node::Text(text) = heading.children.into_iter().nth(0).unwrap() else {
-            panic!("Markdown headings must contain plain text.")
-        };
+    pub fn push_heading(&mut self, heading: Heading) -> Result<(), ParseError> {
+        let position = heading.position.ok_or(ParseError::MissingPosition)?;
+        let span = Span::from(&position);
+        let line = span.start_line;
+        let (children, comments) = take_comments(heading.children);
+        let title = inline_text(&children)
+            .ok_or(ParseError::NonTextHeading { line })?
+            .trim()
+            .to_string();
+        let name = comments
+            .iter()
+            .flat_map(|c| parse_directives(c))
+            .filter(|(key, _)| key == "name")
+            .map(|(_, value)| value)
+            .last()
+            .unwrap_or_else(|| title.clone());
         let depth = heading.depth;
+        for section in self.sections.iter_mut().filter(|s| s.depth >= depth) {
+            self.ended_unused_options
+                .append(&mut section.unused_options);
+        }
         self.sections.retain(|s| s.depth < depth);
+        let base = self
+            .scoped_base
+            .as_ref()
+            .filter(|_| self.sections.is_empty());
         let section = Section {
             depth,
-            line: heading.position.unwrap().start.line,
-            name: text.value,
-            options: self.get_options().clone(),
+            line,
+            span,
+            range: position.start.offset..position.end.offset,
+            slug: self.slugger.slug(&title),
+            name,
+            title,
+            options: match base {
+                Some((options, _)) => Arc::clone(options),
+                None => Arc::clone(self.shared_options()),
+            },
+            options_trace: match base {
+                Some((_, options_trace)) => options_trace.clone(),
+                None => self.get_options_trace().clone(),
+            },
+            local_options: false,
+            setup: self.get_setup().to_vec(),
+            tags: self.get_tags().to_vec(),
+            unused_options: vec![],
+            skipped: self.is_skipped() || comments.iter().any(|c| c == "skip"),
+            focused: self.is_focused() || comments.iter().any(|c| c == "only"),
         };
         self.sections.push(section);
+        for comment in &comments {
+            self.add_tags(parse_tags(comment));
+        }
+        Ok(())
     }
 
-    pub fn set_options(&mut self, options: Options) {
+    pub fn set_options(&mut self, options: Options, options_trace: OptionsTrace) {
         if let Some(last_section) = self.sections.last_mut() {
-            last_section.options = options;
+            last_section.options = Arc::new(options);
+            last_section.options_trace = options_trace;
+            last_section.local_options = true;
         } else {
-            self.root_options = options;
+            self.root_options = Arc::new(options);
+            self.root_options_trace = options_trace;
+            self.root_local_options = true;
+        }
+    }
+
+    pub fn merge_options(
+        &mut self,
+        source: String,
+        line: usize,
+        end_line: usize,
+    ) -> Result<(), ParseError> {
+        let options = merge_options(self.get_options(), source.clone()).map_err(|message| {
+            ParseError::OptionsMerge {
+                line,
+                end_line,
+                source: source.clone(),
+                message,
+            }
+        })?;
+        let mut options_trace = self.get_options_trace().clone();
+        options_trace.push((line, source));
+        self.set_options(options, options_trace);
+        Ok(())
+    }
+
+    /// Merges frontmatter into the root options. Unlike an options block, it
+    /// applies to every section even when options are scoped to sections.
+    pub fn merge_frontmatter(&mut self, source: String, end_line: usize) -> Result<(), ParseError> {
+        self.merge_options(source, 1, end_line)?;
+        if let Some(scoped_base) = self.scoped_base.as_mut() {
+            *scoped_base = (
+                Arc::clone(&self.root_options),
+                self.root_options_trace.clone(),
+            );
+        }
+        Ok(())
+    }
+
+    /// Replaces the current options with `source` merged onto the default
+    /// options, discarding anything inherited from ancestor sections.
+    pub fn reset_options(
+        &mut self,
+        source: String,
+        line: usize,
+        end_line: usize,
+    ) -> Result<(), ParseError>
+    where
+        Options: Default,
+    {
+        let options = merge_options(&Options::default(), source.clone()).map_err(|message| {
+            ParseError::OptionsMerge {
+                line,
+                end_line,
+                source: source.clone(),
+                message,
+            }
+        })?;
+        self.set_options(options, vec![(line, source)]);
+        Ok(())
+    }
+
+    pub fn get_options_trace(&self) -> &OptionsTrace {
+        self.sections
+            .last()
+            .map(|s| &s.options_trace)
+            .unwrap_or(&self.root_options_trace)
+    }
+
+    pub fn push_setup(&mut self, setup: String) {
+        match self.sections.last_mut() {
+            Some(last_section) => last_section.setup.push(setup),
+            None => self.root_setup.push(setup),
+        }
+    }
+
+    /// Adds tags to the current section, skipping any it already has.
+    pub fn add_tags(&mut self, new_tags: impl IntoIterator<Item = String>) {
+        let tags = match self.sections.last_mut() {
+            Some(last_section) => &mut last_section.tags,
+            None => &mut self.root_tags,
+        };
+        for tag in new_tags {
+            if !tags.contains(&tag) {
+                tags.push(tag);
+            }
+        }
+    }
+
+    /// Records an options block in the current section, to be reported if
+    /// no test case uses it.
+    pub fn add_options_block(&mut self, line: usize) {
+        let unused_options = match self.sections.last_mut() {
+            Some(last_section) => &mut last_section.unused_options,
+            None => &mut self.root_unused_options,
+        };
+        if unused_options.last() != Some(&line) {
+            unused_options.push(line);
+        }
+    }
+
+    /// Marks the options blocks of the current section and its ancestors as
+    /// used by a test case.
+    pub fn use_options(&mut self) {
+        self.root_unused_options.clear();
+        for section in &mut self.sections {
+            section.unused_options.clear();
+        }
+    }
+
+    /// Returns the unused options blocks of the sections which have ended,
+    /// or of every section when `finished`.
+    pub fn take_unused_options(&mut self, finished: bool) -> Vec<usize> {
+        let mut lines = take(&mut self.ended_unused_options);
+        if finished {
+            lines.append(&mut self.root_unused_options);
+            for section in &mut self.sections {
+                lines.append(&mut section.unused_options);
+            }
         }
+        lines
     }
 
     pub fn get_options(&self) -> &Options {
+        self.shared_options()
+    }
+
+    fn shared_options(&self) -> &Arc<Options> {
         self.sections
             .last()
             .map(|s| &s.options)
-            .unwrap_or_else(|| &self.root_options)
+            .unwrap_or(&self.root_options)
+    }
+
+    pub fn get_setup(&self) -> &[String] {
+        self.sections
+            .last()
+            .map(|s| s.setup.as_slice())
+            .unwrap_or(&self.root_setup)
+    }
+
+    pub fn get_tags(&self) -> &[String] {
+        self.sections
+            .last()
+            .map(|s| s.tags.as_slice())
+            .unwrap_or(&self.root_tags)
+    }
+
+    pub fn get_name(&self) -> Option<&str> {
+        self.sections.last().map(|s| s.name.as_str())
+    }
+
+    pub fn has_local_options(&self) -> bool {
+        match self.sections.last() {
+            Some(section) => section.local_options,
+            None => self.root_local_options,
+        }
+    }
+
+    pub fn get_title(&self) -> Option<&str> {
+        self.sections.last().map(|s| s.title.as_str())
     }
 
     pub fn get_headings(&self) -> Vec<String> {
         self.sections.iter().map(|s| s.name.clone()).collect()
     }
+
+    pub fn get_slugs(&self) -> Vec<String> {
+        self.sections.iter().map(|s| s.slug.clone()).collect()
+    }
+
+    pub fn get_depths(&self) -> Vec<u8> {
+        self.sections.iter().map(|s| s.depth).collect()
+    }
+
+    pub fn get_lines(&self) -> Vec<usize> {
+        self.sections.iter().map(|s| s.line).collect()
+    }
+
+    pub fn is_skipped(&self) -> bool {
+        self.sections.last().is_some_and(|s| s.skipped)
+    }
+
+    pub fn is_focused(&self) -> bool {
+        self.sections.last().is_some_and(|s| s.focused)
+    }
+
+    pub fn get_line(&self) -> usize {
+        self.sections.last().map(|s| s.line).unwrap_or(0)
+    }
+
+    pub fn get_column(&self) -> usize {
+        self.sections.last().map(|s| s.span.start_col).unwrap_or(0)
+    }
+
+    pub fn get_span(&self) -> Option<Span> {
+        self.sections.last().map(|s| s.span)
+    }
+
+    pub fn get_range(&self) -> Option<Range<usize>> {
+        self.sections.last().map(|s| s.range.clone())
+    }
+
+    pub fn get_depth(&self) -> Option<u8> {
+        self.sections.last().map(|s| s.depth)
+    }
+}
+
+/// A range of lines and columns within a markdown document. Columns are
+/// 1-indexed, and the end is exclusive.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Span {
+    pub start_line: usize,
+    pub start_col: usize,
+    pub end_line: usize,
+    pub end_col: usize,
+}
+
+impl Span {
+    /// Returns a span from the start of `self` to the end of `other`.
+    fn extend(self, other: Span) -> Span {
+        Span {
+            end_line: other.end_line,
+            end_col: other.end_col,
+            ..self
+        }
+    }
+}
+
+impl From<&unist::Position> for Span {
+    fn from(position: &unist::Position) -> Self {
+        Span::from(Position::from(position))
+    }
+}
+
+impl From<Position> for Span {
+    fn from(position: Position) -> Self {
+        Span {
+            start_line: position.start.line,
+            start_col: position.start.column,
+            end_line: position.end.line,
+            end_col: position.end.column,
+        }
+    }
+}
+
+/// A place in a markdown document, mirroring the markdown crate's
+/// [`unist::Point`] so that callers don't need to depend on it. Lines and
+/// columns are 1-indexed, and `offset` is 0-indexed in bytes.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Point {
+    pub line: usize,
+    pub column: usize,
+    pub offset: usize,
+}
+
+impl From<&unist::Point> for Point {
+    fn from(point: &unist::Point) -> Self {
+        Point {
+            line: point.line,
+            column: point.column,
+            offset: point.offset,
+        }
+    }
+}
+
+/// The start and end of a node in a markdown document, mirroring the markdown
+/// crate's [`unist::Position`], e.g. for a [`Boundary`] which inspects
+/// `node.position()`. The end is exclusive.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Position {
+    pub start: Point,
+    pub end: Point,
+}
+
+impl From<&unist::Position> for Position {
+    fn from(position: &unist::Position) -> Self {
+        Position {
+            start: Point::from(&position.start),
+            end: Point::from(&position.end),
+        }
+    }
+}
+
+/// A code block passed to a test case as a positional argument.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Arg {
+    pub value: String,
+    /// The language of the fenced code block, e.g. `sql` for ```` ```sql ````.
+    pub lang: Option<String>,
+    /// Anything following the language on the opening fence.
+    pub meta: Option<String>,
+    /// The line of the opening fence, or the test case's line number if the
+    /// code block has no position.
+    pub line: usize,
+    /// The column where the arg starts, alongside `line`.
+    pub column: usize,
+    /// The cells of a GFM table arg, when [`Config::collect_tables`] is set. The
+    /// `value` of a table arg is its markdown source.
+    pub table: Option<ArgTable>,
+    pub role: ArgRole,
+    /// The label given by a `<!-- arg: query -->` comment immediately before
+    /// the code block.
+    pub label: Option<String>,
+    /// The arg rendered as HTML, e.g. a `<pre>` for a code block or a `<table>`
+    /// for a table. Only rendered with the `render_html` feature, and `None`
+    /// without it or if rendering failed.
+    pub html: Option<String>,
+}
+
+/// Whether an arg is an input to the code under test or an expected output.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ArgRole {
+    /// Any arg which isn't tagged with `expected`.
+    #[default]
+    Input,
+    /// A code block with the language `expected`, or with `expected` in its
+    /// meta, e.g. ```` ```json expected ````.
+    Expected,
+}
+
+impl ArgRole {
+    fn from_fence(lang: Option<&str>, meta: Option<&str>) -> Self {
+        let is_expected = lang == Some("expected")
+            || meta.is_some_and(|meta| meta_tokens(meta).any(|t| t == "expected"));
+        if is_expected {
+            ArgRole::Expected
+        } else {
+            ArgRole::Input
+        }
+    }
+}
+
+impl Arg {
+    /// Compares args by content, ignoring where they are in the document.
+    pub fn semantic_eq(&self, other: &Arg) -> bool {
+        self.value == other.value
+            && self.lang == other.lang
+            && self.meta == other.meta
+            && self.table == other.table
+            && self.role == other.role
+            && self.label == other.label
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ArgTable {
+    pub headers: Vec<String>,
+    pub rows: Vec<Vec<String>>,
+}
+
+impl ArgTable {
+    fn from_rows(rows: Vec<Node>) -> Self {
+        let mut rows = rows.iter().map(|row| {
+            row.children()
+                .into_iter()
+                .flatten()
+                .map(|cell| cell.to_string())
+                .collect::<Vec<_>>()
+        });
+        ArgTable {
+            headers: rows.next().unwrap_or_default(),
+            rows: rows.collect(),
+        }
+    }
 }
 
-#[derive(Debug, Default, PartialEq, Eq)]
-pub struct TestCase<Options: MergeSerialized> {
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TestCase<Options: TryMergeSerialized> {
+    /// The text of the heading the test case lives under, or the value of a
+    /// `<!-- name: ... -->` comment on the heading.
     pub name: String,
+    /// The text of the heading the test case lives under, even when it has a
+    /// `<!-- name: ... -->` comment. Otherwise equal to `name`.
+    pub title: String,
     pub headings: Vec<String>,
+    /// GitHub-style anchor of the heading the test case lives under.
+    pub anchor: String,
+    /// GitHub-style anchors of each of the `headings`.
+    pub heading_slugs: Vec<String>,
+    /// The depth (1 for `#`, 2 for `##`, etc.) of each of the `headings`.
+    pub heading_depths: Vec<u8>,
+    /// The line of each of the `headings`.
+    pub heading_lines: Vec<usize>,
+    /// The depth of the heading the test case lives under, or 0 for test
+    /// cases before the first heading.
+    pub depth: u8,
+    /// The line of the heading the test case lives under. For test cases
+    /// before the first heading, the line of the first arg instead, or 0 if
+    /// there are no args.
     pub line_number: usize,
+    /// The column of the heading's first character, usually 1, or 0 for test
+    /// cases before the first heading.
+    pub column: usize,
     pub options: Options,
-    pub args: Vec<String>,
+    /// The line and source of each options block which contributed to
+    /// `options`, in the order they were merged. A block tagged with
+    /// `options reset` clears the blocks before it. Empty when the test case
+    /// uses the root options as they were passed in.
+    pub options_trace: OptionsTrace,
+    /// Set when an options block in the test case's own section applied to it,
+    /// or, for test cases before the first heading, an options block before
+    /// the first heading or frontmatter. Unset when all of its options were
+    /// inherited from ancestor sections or the root options.
+    pub options_inherited: bool,
+    pub args: Vec<Arg>,
+    /// The values of code blocks with a `title="name"` meta token, keyed by
+    /// the title, when [`Config::args_as_map`] is set.
+    pub named_args: BTreeMap<String, String>,
+    /// The values of code blocks tagged with `setup` in the test case's section
+    /// and its ancestors, outermost first.
+    pub setup: Vec<String>,
+    /// The region of the document the test case was read from, from its
+    /// heading to the end of its last arg.
+    pub span: Span,
+    /// The byte offsets of the `span` within the document, for splicing in
+    /// replacements. `None` when neither the heading nor the args have a
+    /// position.
+    pub byte_range: Option<Range<usize>>,
+    /// The text of any paragraphs between the heading and the first arg,
+    /// joined with blank lines.
+    pub description: Option<String>,
+    /// Key/value pairs from comments like `<!-- tag: slow; timeout: 30 -->`
+    /// within the test case's section.
+    pub attributes: BTreeMap<String, String>,
+    /// Tags from `<!-- tags: network, slow -->` comments on the test case's
+    /// heading or any of its ancestors, or within any of their sections,
+    /// outermost first and without duplicates.
+    pub tags: Vec<String>,
+    /// The markdown file the test case was read from, if any.
+    pub source: Option<PathBuf>,
+    /// Set when the test case's heading, or any of its ancestors, is annotated
+    /// with `<!-- skip -->`.
+    pub skipped: bool,
+    /// Set when the test case's heading, or any of its ancestors, is annotated
+    /// with `<!-- only -->`. See [`TestCase::filter_focused`].
+    pub focused: bool,
 }
 
-impl<Options: MergeSerialized + Clone> TestCase<Options> {
-    fn new(args: Vec<String>, section_stack: &SectionStack<Options>) -> TestCase<Options> {
+impl<Options: TryMergeSerialized> TestCase<Options> {
+    fn new(
+        args: Vec<Arg>,
+        description: Option<String>,
+        section_stack: &SectionStack<Options>,
+        unnamed_name: &str,
+    ) -> TestCase<Options>
+    where
+        Options: Clone,
+    {
         let options = section_stack.get_options().clone();
+        let options_trace = section_stack.get_options_trace().clone();
+        let options_inherited = section_stack.has_local_options();
         let mut headings = section_stack.get_headings();
-        let name = headings
+        let name = headings.pop().unwrap_or_else(|| unnamed_name.to_string());
+        let title = section_stack.get_title().unwrap_or(&name).to_string();
+        let mut heading_slugs = section_stack.get_slugs();
+        let anchor = heading_slugs.pop().unwrap_or_default();
+        let mut heading_depths = section_stack.get_depths();
+        let depth = heading_depths.pop().unwrap_or_default();
+        let mut heading_lines = section_stack.get_lines();
+        let line_number = heading_lines
             .pop()
-            .unwrap_or_else(|| "(Unnamed test)".to_string());
+            .or_else(|| args.first().map(|arg| arg.line))
+            .unwrap_or(0);
         TestCase {
             name,
+            title,
             headings,
-            line_number: section_stack.sections.last().map(|s| s.line).unwrap_or(0),
+            anchor,
+            heading_slugs,
+            heading_depths,
+            heading_lines,
+            depth,
+            line_number,
+            column: section_stack.get_column(),
             options,
+            options_trace,
+            options_inherited,
             args,
+            named_args: BTreeMap::new(),
+            setup: section_stack.get_setup().to_vec(),
+            span: Span::default(),
+            byte_range: None,
+            description,
+            attributes: BTreeMap::new(),
+            tags: section_stack.get_tags().to_vec(),
+            source: None,
+            skipped: section_stack.is_skipped(),
+            focused: section_stack.is_focused(),
+        }
+    }
+
+    /// Returns only the focused test cases if there are any, otherwise all of
+    /// them. Focusing a case doesn't unskip it.
+    pub fn filter_focused(cases: Vec<TestCase<Options>>) -> Vec<TestCase<Options>> {
+        if cases.iter().any(|c| c.focused) {
+            cases.into_iter().filter(|c| c.focused).collect()
+        } else {
+            cases
+        }
+    }
+
+    /// Joins the headings and name of the test case with `sep`. Root-level
+    /// test cases have no headings, so their path is just their name.
+    pub fn path(&self, sep: &str) -> String {
+        self.headings
+            .iter()
+            .map(String::as_str)
+            .chain([self.name.as_str()])
+            .collect::<Vec<_>>()
+            .join(sep)
+    }
+
+    /// Compares the name, headings, options, setup and args of two test cases,
+    /// named args included, ignoring their positions and source file, so that reformatting a
+    /// document doesn't change the result. The derived `PartialEq` compares
+    /// every field.
+    pub fn semantic_eq(&self, other: &TestCase<Options>) -> bool
+    where
+        Options: PartialEq,
+    {
+        self.name == other.name
+            && self.headings == other.headings
+            && self.options == other.options
+            && self.setup == other.setup
+            && self.args.len() == other.args.len()
+            && self
+                .args
+                .iter()
+                .zip(&other.args)
+                .all(|(a, b)| a.semantic_eq(b))
+            && self.named_args == other.named_args
+    }
+
+    /// The args with the [`ArgRole::Input`] role, in order.
+    pub fn inputs(&self) -> impl Iterator<Item = &Arg> {
+        self.args.iter().filter(|arg| arg.role == ArgRole::Input)
+    }
+
+    /// The first arg with the [`ArgRole::Expected`] role, if any.
+    pub fn expected(&self) -> Option<&Arg> {
+        self.args.iter().find(|arg| arg.role == ArgRole::Expected)
+    }
+
+    /// The first arg labeled `label`. See [`TestCase::args_by_label`] for all
+    /// of them.
+    pub fn arg_by_label<'a>(&'a self, label: &'a str) -> Option<&'a Arg> {
+        self.args_by_label(label).next()
+    }
+
+    /// The args labeled `label` with a `<!-- arg: ... -->` comment, in order.
+    pub fn args_by_label<'a>(&'a self, label: &'a str) -> impl Iterator<Item = &'a Arg> + 'a {
+        self.args
+            .iter()
+            .filter(move |arg| arg.label.as_deref() == Some(label))
+    }
+
+    /// The [path](TestCase::path) of the test case, separated with `" > "`.
+    pub fn full_name(&self) -> String {
+        self.path(" > ")
+    }
+
+    /// An identifier which stays the same as long as the test case's headings
+    /// and name do, for tracking results across runs. Test cases with the same
+    /// path get the same ID, see [`Config::detect_duplicates`].
+    ///
+    /// It's the 64-bit FNV-1a hash of the UTF-8 [path](TestCase::path) joined
+    /// with the `\x1f` (unit separator) character, as 16 lowercase hex digits.
+    pub fn stable_id(&self) -> String {
+        let mut hash: u64 = 0xcbf29ce484222325;
+        for byte in self.path("\x1f").bytes() {
+            hash ^= u64::from(byte);
+            hash = hash.wrapping_mul(0x100000001b3);
         }
+        format!("{:016x}", hash)
     }
 }
 
-pub fn get_test_cases<Options: MergeSerialized + Clone>(
+/// A one-line summary like `Tests > Fruits > Apple (line 10, 2 args)`.
+impl<Options: TryMergeSerialized> Display for TestCase<Options> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let plural = if self.args.len() == 1 { "" } else { "s" };
+        write!(
+            f,
+            "{} (line {}, {} arg{})",
+            self.full_name(),
+            self.line_number,
+            self.args.len(),
+            plural
+        )
+    }
+}
+
+pub fn get_test_cases<Options: TryMergeSerialized + Clone + Default>(
     content: String,
     root_options: Options,
-) -> Vec<TestCase<Options>> {
-    let ast = to_mdast(&content, &ParseOptions::default()).unwrap();
-    let Node::Root(root_node) = ast else {
-        panic!("No root node found")
-    };
-    let nodes = root_node.children;
-    let mut section_stack = SectionStack::new(root_options);
-    let mut test_cases: Vec<TestCase<Options>> = vec![];
-    let mut args: Vec<String> = vec![];
-    let mut push_test_case = |s: &SectionStack<Options>, a: &mut Vec<String>| {
-        if a.len() > 0 {
-            test_cases.push(TestCase::new(take(a), &s));
+) -> Result<Vec<TestCase<Options>>, ParseError> {
+    TestCaseExtractor::new(root_options).parse(content)
+}
+
+pub fn get_test_cases_with_config<Options: TryMergeSerialized + Clone + Default>(
+    content: String,
+    root_options: Options,
+    config: &Config,
+) -> Result<Vec<TestCase<Options>>, ParseError> {
+    TestCaseExtractor::new(root_options)
+        .config(config.clone())
+        .parse(content)
+}
+
+/// Like [`get_test_cases`], but first merges each of `layers` onto `base`
+/// in order, exactly like options blocks, to form the root options. Useful
+/// for layering e.g. global defaults and environment-specific settings.
+pub fn get_test_cases_layered<Options: TryMergeSerialized + Clone + Default>(
+    content: String,
+    base: Options,
+    layers: Vec<String>,
+) -> Result<Vec<TestCase<Options>>, ParseError> {
+    let mut root_options = base;
+    for (index, layer) in layers.into_iter().enumerate() {
+        root_options = merge_options(&root_options, layer)
+            .map_err(|message| ParseError::OptionsLayer { index, message })?;
+    }
+    get_test_cases(content, root_options)
+}
+
+/// Like [`get_test_cases`], but for a document which has already been parsed
+/// into a [`Node::Root`], e.g. by a linter, to avoid parsing it again. Any other
+/// node is a [`ParseError::Markdown`]. Without the source text, table args
+/// have an empty `value` and indented code blocks are treated as fenced.
+pub fn get_test_cases_from_ast<Options: TryMergeSerialized + Clone + Default>(
+    root: Node,
+    root_options: Options,
+) -> Result<Vec<TestCase<Options>>, ParseError> {
+    TestCaseExtractor::new(root_options).parse_ast(root)
+}
+
+/// Lazily yields test cases as the document is walked, without buffering them
+/// all in memory. Iteration stops after the first error.
+pub fn iter_test_cases<Options: TryMergeSerialized + Clone + Default>(
+    content: String,
+    root_options: Options,
+) -> impl Iterator<Item = Result<TestCase<Options>, ParseError>> {
+    TestCaseExtractor::new(root_options).iter(content)
+}
+
+/// Extracts only the first `limit` test cases, for a quick smoke test. The rest
+/// of the document isn't walked, so errors after the cutoff aren't reported.
+pub fn get_test_cases_limited<Options: TryMergeSerialized + Clone + Default>(
+    content: String,
+    root_options: Options,
+    limit: usize,
+) -> Result<Vec<TestCase<Options>>, ParseError> {
+    iter_test_cases(content, root_options).take(limit).collect()
+}
+
+/// Calls `f` with each test case as soon as it's complete, stopping at the
+/// first error. Useful for streaming test cases elsewhere without collecting
+/// them.
+pub fn visit_test_cases<Options, F>(
+    content: String,
+    root_options: Options,
+    f: F,
+) -> Result<(), ParseError>
+where
+    Options: TryMergeSerialized + Clone + Default,
+    F: FnMut(TestCase<Options>),
+{
+    TestCaseExtractor::new(root_options).visit(content, f)
+}
+
+/// Like [`get_test_cases`], but also returns any [`Warning`]s about the
+/// document, ordered by line.
+pub fn get_test_cases_with_warnings<Options: TryMergeSerialized + Clone + Default>(
+    content: String,
+    root_options: Options,
+) -> Result<(Vec<TestCase<Options>>, Vec<Warning>), ParseError> {
+    TestCaseExtractor::new(root_options).parse_with_warnings(content)
+}
+
+/// Parses the document and merges every options block like [`get_test_cases`],
+/// but only counts the test cases rather than collecting them.
+pub fn validate<Options: TryMergeSerialized + Clone + Default>(
+    content: String,
+    root_options: Options,
+) -> Result<usize, ParseError> {
+    TestCaseExtractor::new(root_options).validate(content)
+}
+
+/// The options in effect for test cases directly under the section with the
+/// headings `path`, e.g. `&["Tests", "Fruits"]`, or `&[]` for test cases before
+/// the first heading. Only as much of the document as needed is walked. When
+/// several sections have the same headings, the first one is used. Fails with
+/// [`ParseError::UnknownPath`] if there's no such section.
+pub fn options_at_path<Options: TryMergeSerialized + Clone + Default>(
+    content: String,
+    root_options: Options,
+    path: &[&str],
+) -> Result<Options, ParseError> {
+    TestCaseExtractor::new(root_options).options_at_path(content, path)
+}
+
+struct TestCaseIter<Options: TryMergeSerialized + Clone + Default> {
+    config: Config,
+    content: String,
+    nodes: std::vec::IntoIter<Node>,
+    /// Children of nodes being scanned with [`Config::deep_scan`], last first.
+    nested: Vec<Node>,
+    section_stack: SectionStack<Options>,
+    args: Vec<Arg>,
+    /// Paragraphs seen since the last heading, before any args.
+    description: Vec<String>,
+    /// The combined position of the args collected so far.
+    args_span: Option<Span>,
+    /// The combined byte offsets of the args collected so far.
+    args_range: Option<Range<usize>>,
+    /// Directives from HTML comments seen since the last test case.
+    attributes: BTreeMap<String, String>,
+    /// The label from an `arg` directive, for the code block which follows.
+    label: Option<String>,
+    /// Whether the current section has produced a test case yet.
+    section_has_case: bool,
+    /// The file being parsed, recorded on each test case.
+    source: Option<PathBuf>,
+    /// The line of each heading path seen so far.
+    seen_paths: HashMap<String, usize>,
+    /// Test cases of the current section, held back until it ends with
+    /// [`Config::options_apply_to_section`].
+    held: Vec<TestCase<Options>>,
+    /// Test cases ready to be yielded before walking any further.
+    queue: VecDeque<TestCase<Options>>,
+    warnings: Vec<Warning>,
+    error: Option<ParseError>,
+    done: bool,
+}
+
+impl<Options: TryMergeSerialized + Clone + Default> TestCaseIter<Options> {
+    fn new(content: String, root_options: Options, config: Config) -> Self {
+        let root = to_mdast(&content, &config.parse_options())
+            .map_err(|e| ParseError::Markdown(e.to_string()));
+        Self::from_root(root, content, root_options, config)
+    }
+
+    /// Walks an already parsed document. `content` is its source, if known,
+    /// which table args and indented code detection rely on.
+    fn from_root(
+        root: Result<Node, ParseError>,
+        content: String,
+        root_options: Options,
+        config: Config,
+    ) -> Self {
+        let (nodes, error) = match root {
+            Ok(Node::Root(root_node)) => (root_node.children, None),
+            Ok(_) => (
+                vec![],
+                Some(ParseError::Markdown("No root node found".to_string())),
+            ),
+            Err(error) => (vec![], Some(error)),
+        };
+        let section_stack = SectionStack::new(root_options, config.scope_options_to_section);
+        Self {
+            config,
+            content,
+            nodes: nodes.into_iter(),
+            nested: vec![],
+            section_stack,
+            args: vec![],
+            description: vec![],
+            args_span: None,
+            args_range: None,
+            attributes: BTreeMap::new(),
+            label: None,
+            section_has_case: false,
+            source: None,
+            seen_paths: HashMap::new(),
+            held: vec![],
+            queue: VecDeque::new(),
+            warnings: vec![],
+            error,
+            done: false,
         }
-    };
-    for node in nodes {
-        match node {
-            Node::Heading(heading) => {
-                push_test_case(&section_stack, &mut args);
-                section_stack.push_heading(heading);
-            }
-            Node::Code(code) => {
-                if code.meta.as_deref() == Some("options") {
-                    let options = section_stack
-                        .get_options()
-                        .merge_serialized(code.value)
-                        .unwrap_or_else(|error| {
-                            let line = code.position.unwrap().start.line;
-                            panic!(
-                                "Failed to parse options from code block at line {}: {}",
-                                line, error
-                            );
-                        });
-                    section_stack.set_options(options)
-                } else {
-                    args.push(code.value)
+    }
+
+    /// Finalizes the args collected so far into a test case, if there are any.
+    /// `next_depth` is the depth of the heading which ends the test case, or
+    /// `None` at the end of the document.
+    fn flush(&mut self, next_depth: Option<u8>) -> Result<Option<TestCase<Options>>, ParseError> {
+        if self.args.is_empty() && !self.is_empty_leaf(next_depth) {
+            return Ok(None);
+        }
+        // Test cases split from one section share its name, so they aren't
+        // duplicates of each other.
+        let is_split = self.section_has_case;
+        self.section_has_case = true;
+        let description = take(&mut self.description);
+        let description = (!description.is_empty()).then(|| description.join("\n\n"));
+        let mut test_case = TestCase::new(
+            take(&mut self.args),
+            description,
+            &self.section_stack,
+            &self.config.unnamed_name,
+        );
+        test_case.source = self.source.clone();
+        test_case.span = self.take_span();
+        test_case.byte_range = self.take_byte_range();
+        test_case.attributes = take(&mut self.attributes);
+        match self.config.max_args_per_case {
+            Some(max) if test_case.args.len() > max => {
+                self.warnings.push(Warning::TooManyArgs {
+                    name: test_case.full_name(),
+                    line: test_case.line_number,
+                    count: test_case.args.len(),
+                });
+                if self.config.truncate_args {
+                    test_case.args.truncate(max);
                 }
             }
             _ => {}
         }
+        if self.config.args_as_map {
+            let (named, positional): (Vec<Arg>, _) =
+                take(&mut test_case.args).into_iter().partition(|arg| {
+                    arg.meta
+                        .as_deref()
+                        .and_then(|meta| meta_value(meta, "title"))
+                        .is_some()
+                });
+            test_case.args = positional;
+            test_case.named_args = named
+                .into_iter()
+                .filter_map(|arg| {
+                    let title = meta_value(arg.meta.as_deref()?, "title")?.to_string();
+                    Some((title, arg.value))
+                })
+                .collect();
+        }
+        self.section_stack.use_options();
+        let name = test_case.full_name();
+        let second_line = test_case.line_number;
+        match self.seen_paths.get(&name) {
+            _ if is_split => {}
+            Some(&first_line) if self.config.detect_duplicates => {
+                return Err(ParseError::DuplicateName {
+                    name,
+                    first_line,
+                    second_line,
+                });
+            }
+            Some(&first_line) => self.warnings.push(Warning::DuplicateName {
+                name,
+                first_line,
+                second_line,
+            }),
+            None => {
+                self.seen_paths.insert(name, second_line);
+            }
+        }
+        Ok(Some(test_case))
     }
-    push_test_case(&section_stack, &mut args);
-    test_cases
-}
-
-#[cfg(test)]
-mod tests {
-    use crate::{get_test_cases, MergeSerialized, TestCase};
-    use std::path::PathBuf;
-    use toml::{from_str, Table};
 
-    #[derive(Default, PartialEq, Eq, Debug, Clone, Copy)]
-    struct Options {
-        foo: i64,
-        bar: bool,
+    /// Ends the current test case within its section, holding it back with
+    /// [`Config::options_apply_to_section`].
+    fn split(&mut self) -> Result<Option<TestCase<Options>>, ParseError> {
+        let test_case = self.flush(None)?;
+        if !self.config.options_apply_to_section {
+            return Ok(test_case);
+        }
+        self.held.extend(test_case);
+        Ok(None)
     }
 
-    impl MergeSerialized for Options {
-        fn merge_serialized(&self, source: String) -> Result<Self, String> {
-            let values = from_str::<Table>(&source).map_err(|e| e.to_string())?;
-            Ok(Options {
-                foo: values
-                    .get("foo")
-                    .and_then(|v| v.as_integer())
-                    .unwrap_or(self.foo),
-                bar: values
-                    .get("bar")
-                    .and_then(|v| v.as_bool())
-                    .unwrap_or(self.bar),
-            })
+    /// The options at the end of the first section with the headings `path`.
+    /// See [`options_at_path`].
+    fn options_at(mut self, path: &[&str]) -> Result<Options, ParseError> {
+        if let Some(error) = self.error.take() {
+            return Err(error);
+        }
+        let mut options = path
+            .is_empty()
+            .then(|| self.section_stack.get_options().clone());
+        while let Some(node) = self.next_node() {
+            self.handle_node(node)?;
+            if let Some(error) = self.error.take() {
+                return Err(error);
+            }
+            if self.section_stack.get_headings() == path {
+                options = Some(self.section_stack.get_options().clone());
+            } else if options.is_some() {
+                break;
+            }
+        }
+        options.ok_or_else(|| ParseError::UnknownPath {
+            path: path.join(" > "),
+        })
+    }
+
+    /// Releases any test cases held back from the section which is ending,
+    /// giving them its final options, followed by `test_case`, the section's
+    /// last. Returns the first of them, queueing the rest.
+    fn end_section(&mut self, test_case: Option<TestCase<Options>>) -> Option<TestCase<Options>> {
+        if self.held.is_empty() {
+            return test_case;
+        }
+        for case in &mut self.held {
+            case.options = self.section_stack.get_options().clone();
+            case.options_trace = self.section_stack.get_options_trace().clone();
+            case.options_inherited = self.section_stack.has_local_options();
+        }
+        self.section_stack.use_options();
+        self.queue.extend(take(&mut self.held));
+        self.queue.extend(test_case);
+        self.queue.pop_front()
+    }
+
+    /// The span from the start of the current heading to the end of the last
+    /// arg, or of the args alone when there is no heading.
+    fn take_span(&mut self) -> Span {
+        let args_span = self.args_span.take();
+        match (self.section_stack.get_span(), args_span) {
+            (Some(heading_span), Some(args_span)) => heading_span.extend(args_span),
+            (Some(heading_span), None) => heading_span,
+            (None, Some(args_span)) => args_span,
+            (None, None) => Span::default(),
+        }
+    }
+
+    /// The byte offsets of the span from [`TestCaseIter::take_span`].
+    fn take_byte_range(&mut self) -> Option<Range<usize>> {
+        let args_range = self.args_range.take();
+        match (self.section_stack.get_range(), args_range) {
+            (Some(heading_range), Some(args_range)) => Some(heading_range.start..args_range.end),
+            (heading_range, args_range) => heading_range.or(args_range),
+        }
+    }
+
+    /// Whether the current section should produce a test case despite having
+    /// no args, per [`Config::include_empty`].
+    fn is_empty_leaf(&self, next_depth: Option<u8>) -> bool {
+        let Some(depth) = self.section_stack.get_depth() else {
+            return false;
+        };
+        self.config.include_empty
+            && !self.section_has_case
+            && next_depth.is_none_or(|next_depth| next_depth <= depth)
+    }
+
+    /// Collects an arg, filling in its line and extending the span of the test
+    /// case from the arg's position.
+    fn push_arg(&mut self, arg: Arg, position: Option<&unist::Position>) {
+        let (line, column) = match position {
+            Some(position) => {
+                let span = Span::from(position);
+                self.args_span = Some(match self.args_span {
+                    Some(args_span) => args_span.extend(span),
+                    None => span,
+                });
+                let end = position.end.offset;
+                self.args_range = Some(match self.args_range.take() {
+                    Some(args_range) => args_range.start..end,
+                    None => position.start.offset..end,
+                });
+                (span.start_line, span.start_col)
+            }
+            None => (
+                self.section_stack.get_line(),
+                self.section_stack.get_column(),
+            ),
+        };
+        let value = self.normalize(arg.value);
+        let value = self.config.trim_args.apply(value);
+        self.args.push(Arg {
+            value,
+            line,
+            column,
+            ..arg
+        });
+    }
+
+    fn normalize(&self, value: String) -> String {
+        if self.config.normalize_newlines {
+            normalize_newlines(value)
+        } else {
+            value
+        }
+    }
+
+    /// Collects a code block as an arg, or as several when it contains
+    /// [`Config::intra_block_delimiter`] lines.
+    fn push_code(&mut self, code: Code, label: Option<String>) {
+        let role = ArgRole::from_fence(code.lang.as_deref(), code.meta.as_deref());
+        let value = self.normalize(code.value);
+        let parts = match &self.config.intra_block_delimiter {
+            Some(delimiter) => split_on_delimiter(&value, delimiter),
+            None => vec![(0, value)],
+        };
+        let lang = code
+            .lang
+            .clone()
+            .or_else(|| self.config.default_arg_lang.clone());
+        for (offset, value) in parts {
+            let html = render_html(&fence(lang.as_deref(), code.meta.as_deref(), &value));
+            let arg = Arg {
+                html,
+                value,
+                lang: lang.clone(),
+                meta: code.meta.clone(),
+                role,
+                label: label.clone(),
+                ..Default::default()
+            };
+            self.push_arg(arg, code.position.as_ref());
+            if code.position.is_some() {
+                if let Some(arg) = self.args.last_mut() {
+                    arg.line += offset;
+                }
+            }
+        }
+    }
+
+    fn push_list_item(&mut self, item: Node) {
+        let value = item
+            .children()
+            .into_iter()
+            .flatten()
+            .filter(|child| !matches!(child, Node::List(_)))
+            .map(|child| child.to_string())
+            .collect::<Vec<_>>()
+            .join("\n");
+        let arg = Arg {
+            html: render_html(&value),
+            value,
+            ..Default::default()
+        };
+        self.push_arg(arg, item.position());
+    }
+
+    /// Recognizes an options block by its meta, i.e. any whitespace-separated
+    /// token matching the options keyword, or by its language when
+    /// [`Config::options_by_lang`] is set. Returns whether the block resets
+    /// the options, i.e. also has a `reset` token, or `None` if it isn't an
+    /// options block. A `literal` token escapes the block, so that it's
+    /// collected as an arg instead. Other tokens, such as `title="x"`, are
+    /// ignored.
+    fn options_block(&self, lang: Option<&str>, meta: Option<&str>) -> Option<bool> {
+        let tokens = meta_tokens(meta.unwrap_or_default()).collect::<Vec<_>>();
+        if tokens.contains(&"literal") {
+            return None;
+        }
+        let is_keyword = |token: &str| self.config.is_options_keyword(token);
+        let is_options = tokens.iter().any(|token| is_keyword(token))
+            || (self.config.options_by_lang && lang.is_some_and(is_keyword));
+        is_options.then(|| tokens.contains(&"reset"))
+    }
+
+    /// The file referenced by a `file="path"` token in the meta of an options
+    /// block, resolved relative to the directory of the markdown file being
+    /// parsed, if any. See [`Config::options_files`].
+    fn options_file(&self, meta: Option<&str>, line: usize) -> Result<Option<PathBuf>, ParseError> {
+        let path = match meta.and_then(|meta| meta_value(meta, "file")) {
+            Some(path) if self.config.options_files => path,
+            _ => return Ok(None),
+        };
+        let dir = self.source.as_deref().and_then(|source| source.parent());
+        match dir {
+            Some(dir) if is_contained(Path::new(path)) => Ok(Some(dir.join(path))),
+            _ => Err(ParseError::OptionsFile {
+                line,
+                path: path.to_owned(),
+            }),
+        }
+    }
+
+    fn apply_options(
+        &mut self,
+        source: String,
+        (line, end_line): (usize, usize),
+        reset: bool,
+    ) -> Result<(), ParseError> {
+        let result = if reset {
+            self.section_stack.reset_options(source, line, end_line)
+        } else {
+            self.section_stack.merge_options(source, line, end_line)
+        };
+        if result.is_ok() {
+            self.section_stack.add_options_block(line);
+        }
+        self.recover(result)
+    }
+
+    /// Turns an options merge error into a warning, per
+    /// [`Config::continue_on_error`].
+    fn recover(&mut self, result: Result<(), ParseError>) -> Result<(), ParseError> {
+        match result {
+            Err(ParseError::OptionsMerge { line, message, .. })
+                if self.config.continue_on_error =>
+            {
+                self.warnings.push(Warning::OptionsMerge { line, message });
+                Ok(())
+            }
+            result => result,
+        }
+    }
+
+    fn next_node(&mut self) -> Option<Node> {
+        self.nested.pop().or_else(|| self.nodes.next())
+    }
+
+    /// Returns the warnings found so far, including unused options blocks in
+    /// sections which are still open once the whole document has been read.
+    fn take_warnings(&mut self) -> Vec<Warning> {
+        let finished = self.nested.is_empty() && self.nodes.as_slice().is_empty();
+        let unused_options = self.section_stack.take_unused_options(finished);
+        self.warnings.extend(
+            unused_options
+                .into_iter()
+                .map(|line| Warning::UnusedOptions { line }),
+        );
+        let mut warnings = take(&mut self.warnings);
+        warnings.sort_by_key(Warning::line);
+        warnings
+    }
+
+    /// Checks that every token in a code block's meta means something to this
+    /// crate, per [`Config::strict`].
+    fn check_meta(&self, meta: Option<&str>, line: usize) -> Result<(), ParseError> {
+        let Some(meta) = meta else {
+            return Ok(());
+        };
+        let is_known = |token: &str| {
+            self.config.is_options_keyword(token)
+                || token.starts_with("file=")
+                || token.starts_with("title=")
+                || ["reset", "literal", "expected", "setup"].contains(&token)
+        };
+        if meta_tokens(meta).all(is_known) {
+            Ok(())
+        } else {
+            Err(ParseError::UnknownMeta {
+                line,
+                meta: meta.to_string(),
+            })
+        }
+    }
+
+    /// Whether a code block is a setup block, i.e. has the language `setup` or
+    /// `setup` in its meta, and isn't escaped with `literal`.
+    fn is_setup_block(lang: Option<&str>, meta: Option<&str>) -> bool {
+        let tokens = meta_tokens(meta.unwrap_or_default()).collect::<Vec<_>>();
+        !tokens.contains(&"literal") && (lang == Some("setup") || tokens.contains(&"setup"))
+    }
+
+    /// Whether a code block is indented rather than fenced, judging by its
+    /// source. Code blocks without a position are assumed to be fenced.
+    fn is_indented_code(&self, position: Option<&unist::Position>) -> bool {
+        position
+            .and_then(|p| self.content.get(p.start.offset..))
+            .is_some_and(|source| {
+                let source = source.trim_start_matches(' ');
+                !source.starts_with("```") && !source.starts_with("~~~")
+            })
+    }
+
+    fn handle_node(&mut self, node: Node) -> Result<Option<TestCase<Options>>, ParseError> {
+        let label = match node {
+            Node::Html(_) => None,
+            _ => self.label.take(),
+        };
+        let is_boundary = self.config.is_boundary(&node);
+        let test_case = if is_boundary && !matches!(node, Node::Heading(_)) && !self.args.is_empty()
+        {
+            self.split()?
+        } else {
+            None
+        };
+        match node {
+            Node::Heading(heading)
+                if self
+                    .config
+                    .max_heading_depth
+                    .is_some_and(|max| heading.depth > max) => {}
+            Node::Heading(_) if !is_boundary => {}
+            Node::Heading(heading)
+                if self.config.merge_child_args
+                    && !self.args.is_empty()
+                    && self
+                        .section_stack
+                        .get_depth()
+                        .is_some_and(|depth| heading.depth > depth) => {}
+            Node::Heading(heading) => {
+                let test_case = self.flush(Some(heading.depth))?;
+                let test_case = self.end_section(test_case);
+                self.description.clear();
+                self.attributes.clear();
+                if let Err(error) = self.section_stack.push_heading(heading) {
+                    if test_case.is_none() {
+                        return Err(error);
+                    }
+                    // Report the error once the test case the heading ended is returned.
+                    self.error = Some(error);
+                    return Ok(test_case);
+                }
+                self.section_has_case = false;
+                if self.section_stack.get_name() == Some("") {
+                    let line = self.section_stack.get_line();
+                    self.warnings.push(Warning::EmptyHeading { line });
+                }
+                return Ok(test_case);
+            }
+            Node::ThematicBreak(_)
+                if self.config.split_on_thematic_break && !self.args.is_empty() =>
+            {
+                return self.split();
+            }
+            Node::Paragraph(_) if self.args.is_empty() => self.description.push(node.to_string()),
+            Node::Html(html) => {
+                if let Some(comment) = comment_text(&html.value) {
+                    for (key, value) in parse_directives(comment) {
+                        if key == "arg" {
+                            self.label = Some(value);
+                        } else {
+                            self.attributes.insert(key, value);
+                        }
+                    }
+                    self.section_stack.add_tags(parse_tags(comment));
+                }
+            }
+            Node::Table(table) if self.config.collect_tables => {
+                let value = table
+                    .position
+                    .as_ref()
+                    .and_then(|p| self.content.get(p.start.offset..p.end.offset))
+                    .unwrap_or_default()
+                    .to_string();
+                let arg = Arg {
+                    html: render_html(&value),
+                    value,
+                    table: Some(ArgTable::from_rows(table.children)),
+                    ..Default::default()
+                };
+                self.push_arg(arg, table.position.as_ref());
+            }
+            Node::List(list) if self.config.collect_list_items => {
+                for item in list.children {
+                    self.push_list_item(item);
+                }
+            }
+            Node::List(List { children, .. })
+            | Node::ListItem(ListItem { children, .. })
+            | Node::Blockquote(Blockquote { children, .. })
+                if self.config.deep_scan =>
+            {
+                // Only code blocks are picked out, so that a quoted heading doesn't
+                // start a section, nor a list item's text become a description.
+                let children = children.into_iter().filter(|child| {
+                    matches!(
+                        child,
+                        Node::Code(_) | Node::List(_) | Node::ListItem(_) | Node::Blockquote(_)
+                    )
+                });
+                self.nested.extend(children.rev());
+            }
+            Node::Yaml(yaml) => {
+                let end_line = yaml.position.map(|p| p.end.line).unwrap_or(1);
+                let result = self.section_stack.merge_frontmatter(yaml.value, end_line);
+                self.recover(result)?;
+            }
+            Node::Toml(toml) => {
+                let end_line = toml.position.map(|p| p.end.line).unwrap_or(1);
+                let result = self.section_stack.merge_frontmatter(toml.value, end_line);
+                self.recover(result)?;
+            }
+            Node::Code(code)
+                if self.config.ignore_indented_code
+                    && self.is_indented_code(code.position.as_ref()) => {}
+            Node::Code(code) => {
+                if self.config.strict {
+                    let line = code.position.as_ref().map(|p| p.start.line).unwrap_or(0);
+                    self.check_meta(code.meta.as_deref(), line)?;
+                }
+                let options_block = self.options_block(code.lang.as_deref(), code.meta.as_deref());
+                if let Some(mut reset) = options_block {
+                    let lines = code
+                        .position
+                        .map(|p| (p.start.line, p.end.line))
+                        .unwrap_or((0, 0));
+                    let file = self.options_file(code.meta.as_deref(), lines.0)?;
+                    if let Some(path) = &file {
+                        let result = self.apply_options(read_file(path)?, lines, reset);
+                        // Report the block's own content rather than the file's.
+                        result.map_err(|error| match error {
+                            ParseError::OptionsMerge {
+                                line,
+                                end_line,
+                                message,
+                                ..
+                            } => ParseError::OptionsMerge {
+                                line,
+                                end_line,
+                                source: code.value.clone(),
+                                message,
+                            },
+                            error => error,
+                        })?;
+                        reset = false;
+                    }
+                    if file.is_none() || !code.value.trim().is_empty() {
+                        self.apply_options(code.value, lines, reset)?;
+                    }
+                } else if Self::is_setup_block(code.lang.as_deref(), code.meta.as_deref()) {
+                    self.section_stack.push_setup(code.value);
+                } else {
+                    self.push_code(code, label);
+                }
+            }
+            _ => {}
+        }
+        Ok(test_case)
+    }
+}
+
+impl<Options: TryMergeSerialized + Clone + Default> Iterator for TestCaseIter<Options> {
+    type Item = Result<TestCase<Options>, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let result = self.advance();
+        if !matches!(result, Some(Ok(_))) {
+            self.done = true;
+        }
+        result
+    }
+}
+
+impl<Options: TryMergeSerialized + Clone + Default> TestCaseIter<Options> {
+    fn advance(&mut self) -> Option<Result<TestCase<Options>, ParseError>> {
+        if let Some(test_case) = self.queue.pop_front() {
+            return Some(Ok(test_case));
+        }
+        if let Some(error) = self.error.take() {
+            return Some(Err(error));
+        }
+        while let Some(node) = self.next_node() {
+            if let Some(result) = self.handle_node(node).transpose() {
+                return Some(result);
+            }
+        }
+        match self.flush(None) {
+            Ok(test_case) => self.end_section(test_case).map(Ok),
+            Err(error) => Some(Err(error)),
+        }
+    }
+}
+
+/// Like [`get_test_cases`], but panics on any parse error.
+pub fn get_test_cases_or_panic<Options: TryMergeSerialized + Clone + Default>(
+    content: String,
+    root_options: Options,
+) -> Vec<TestCase<Options>> {
+    get_test_cases(content, root_options).unwrap_or_else(|error| panic!("{}", error))
+}
+
+/// Splits test cases into batches of `size`, in order, e.g. to dispatch to
+/// workers. The last batch may be smaller. A `size` of 0 puts every test case
+/// in a single batch.
+pub fn chunk_test_cases<Options: TryMergeSerialized>(
+    cases: Vec<TestCase<Options>>,
+    size: usize,
+) -> Vec<Vec<TestCase<Options>>> {
+    if size == 0 {
+        return vec![cases];
+    }
+    let mut chunks = vec![];
+    let mut cases = cases.into_iter().peekable();
+    while cases.peek().is_some() {
+        chunks.push(cases.by_ref().take(size).collect());
+    }
+    chunks
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        chunk_test_cases, get_test_cases, get_test_cases_from_ast, get_test_cases_layered,
+        get_test_cases_limited, get_test_cases_with_config, get_test_cases_with_warnings,
+        iter_test_cases, merge_options, options_at_path, test_cases_from_path, to_markdown,
+        validate, visit_test_cases, Arg, ArgRole, ArgTable, ArgTrim, Config, Constructs,
+        MergeSerialized, Node, ParseError, Point, Position, SerializeOptions, Span, TestCase,
+        TestCaseExtractor, TestCaseIter, ThematicBreakBoundary, TryMergeSerialized, Warning,
+    };
+    use markdown::mdast::Code;
+    use std::{
+        collections::{BTreeMap, HashSet},
+        path::PathBuf,
+    };
+    use toml::{from_str, Table};
+
+    #[derive(Default, PartialEq, Eq, Hash, Debug, Clone, Copy)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    struct Options {
+        foo: i64,
+        bar: bool,
+    }
+
+    impl MergeSerialized for Options {
+        fn merge_serialized(&self, source: String) -> Result<Self, String> {
+            let values = from_str::<Table>(&source).map_err(|e| e.to_string())?;
+            Ok(Options {
+                foo: values
+                    .get("foo")
+                    .and_then(|v| v.as_integer())
+                    .unwrap_or(self.foo),
+                bar: values
+                    .get("bar")
+                    .and_then(|v| v.as_bool())
+                    .unwrap_or(self.bar),
+            })
+        }
+    }
+
+    impl SerializeOptions for Options {
+        const LANG: &'static str = "toml";
+
+        fn serialize_options(&self, base: &Self) -> Option<String> {
+            let mut lines = vec![];
+            if self.foo != base.foo {
+                lines.push(format!("foo = {}", self.foo));
+            }
+            if self.bar != base.bar {
+                lines.push(format!("bar = {}", self.bar));
+            }
+            (!lines.is_empty()).then(|| lines.join("\n"))
+        }
+    }
+
+    fn arg(value: &str, line: usize) -> Arg {
+        Arg {
+            value: value.to_owned(),
+            line,
+            column: 1,
+            html: crate::render_html(&crate::fence(None, None, value)),
+            ..Default::default()
         }
     }
 
@@ -170,30 +1708,1889 @@ mod tests {
     fn test_basic() {
         let path = PathBuf::from_iter([env!("CARGO_MANIFEST_DIR"), "src", "test.md"]);
         let content = std::fs::read_to_string(path).unwrap();
-        let result = get_test_cases(content, Options::default());
+        let result = get_test_cases(content, Options::default()).unwrap();
         let expected = [
             TestCase {
                 name: "Apple".to_owned(),
+                title: "Apple".to_owned(),
                 headings: vec!["Tests".to_owned(), "Fruits".to_owned()],
+                anchor: "apple".to_owned(),
+                span: Span {
+                    start_line: 10,
+                    start_col: 1,
+                    end_line: 18,
+                    end_col: 4,
+                },
+                heading_slugs: vec!["tests".to_owned(), "fruits".to_owned()],
+                heading_depths: vec![1, 2],
+                heading_lines: vec![1, 8],
+                depth: 3,
+                byte_range: Some(60..104),
                 line_number: 10,
+                column: 1,
                 options: Options { foo: 5, bar: true },
-                args: vec!["Granny Smith".to_owned(), "red".to_owned()],
+                options_trace: vec![(3, "foo = 5\nbar = true".to_owned())],
+                args: vec![arg("Granny Smith", 12), arg("red", 16)],
+                ..Default::default()
             },
             TestCase {
                 name: "Pear".to_owned(),
+                title: "Pear".to_owned(),
                 headings: vec!["Tests".to_owned(), "Fruits".to_owned()],
+                anchor: "pear".to_owned(),
+                span: Span {
+                    start_line: 20,
+                    start_col: 1,
+                    end_line: 32,
+                    end_col: 4,
+                },
+                heading_slugs: vec!["tests".to_owned(), "fruits".to_owned()],
+                heading_depths: vec![1, 2],
+                heading_lines: vec![1, 8],
+                depth: 3,
+                byte_range: Some(106..181),
                 line_number: 20,
+                column: 1,
                 options: Options { foo: 5, bar: false },
-                args: vec!["Bartlett".to_owned(), "yellow".to_owned()],
+                options_trace: vec![
+                    (3, "foo = 5\nbar = true".to_owned()),
+                    (22, "bar = false".to_owned()),
+                ],
+                options_inherited: true,
+                args: vec![arg("Bartlett", 26), arg("yellow", 30)],
+                ..Default::default()
             },
             TestCase {
                 name: "Potato".to_owned(),
+                title: "Potato".to_owned(),
                 headings: vec!["Tests".to_owned(), "Vegetables".to_owned()],
+                anchor: "potato".to_owned(),
+                span: Span {
+                    start_line: 40,
+                    start_col: 1,
+                    end_line: 48,
+                    end_col: 4,
+                },
+                heading_slugs: vec!["tests".to_owned(), "vegetables".to_owned()],
+                heading_depths: vec![1, 2],
+                heading_lines: vec![1, 34],
+                depth: 3,
+                byte_range: Some(228..269),
                 line_number: 40,
+                column: 1,
                 options: Options { foo: 11, bar: true },
-                args: vec!["Russet".to_owned(), "brown".to_owned()],
+                options_trace: vec![
+                    (3, "foo = 5\nbar = true".to_owned()),
+                    (36, "foo = 11".to_owned()),
+                ],
+                args: vec![arg("Russet", 42), arg("brown", 46)],
+                ..Default::default()
             },
         ];
         assert_eq!(result, expected);
     }
+
+    #[test]
+    fn test_options_error() {
+        let content = "# A\n\n```toml options\nfoo = \n```\n".to_owned();
+        let result = get_test_cases(content, Options::default());
+        match result {
+            Err(ParseError::OptionsMerge { line, source, .. }) => {
+                assert_eq!(line, 3);
+                assert_eq!(source, "foo = ");
+            }
+            other => panic!("Expected an options error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_non_text_heading() {
+        let content = "# A\n\n## ![img](x.png)\n\n```\narg\n```\n".to_owned();
+        let result = get_test_cases(content, Options::default());
+        assert_eq!(result, Err(ParseError::NonTextHeading { line: 3 }));
+    }
+
+    #[test]
+    fn test_formatted_headings() {
+        let content = [
+            "# The **fast** path",
+            "## `parse()` _edge_ cases",
+            "```\na\n```",
+            "##",
+            "```\nb\n```",
+        ]
+        .join("\n\n");
+        let result = get_test_cases(content, Options::default()).unwrap();
+        assert_eq!(result[0].headings, vec!["The fast path".to_owned()]);
+        assert_eq!(result[0].name, "parse() edge cases");
+        assert_eq!(result[1].name, "");
+    }
+
+    #[test]
+    fn test_options_keyword() {
+        let content =
+            "# A\n\n```toml options\nfoo = 1\n```\n\n```toml opts\nfoo = 2\n```\n".to_owned();
+        let config = Config {
+            options_keyword: "opts".to_owned(),
+            ..Default::default()
+        };
+        let result = get_test_cases_with_config(content, Options::default(), &config).unwrap();
+        assert_eq!(result[0].options.foo, 2);
+        assert_eq!(result[0].args[0].value, "foo = 1");
+    }
+
+    #[test]
+    fn test_frontmatter() {
+        let content = "+++\nfoo = 3\n+++\n\n# A\n\n```\na\n```\n".to_owned();
+        let config = Config {
+            frontmatter: true,
+            ..Default::default()
+        };
+        let result = get_test_cases_with_config(content, Options::default(), &config).unwrap();
+        assert_eq!(result[0].options.foo, 3);
+        assert_eq!(result[0].line_number, 5);
+
+        let content = "+++\nfoo = \n+++\n\n# A\n\n```\na\n```\n".to_owned();
+        let result = get_test_cases_with_config(content, Options::default(), &config);
+        assert!(matches!(
+            result,
+            Err(ParseError::OptionsMerge {
+                line: 1,
+                end_line: 3,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_constructs() {
+        let content = "# ~~Old~~ New\n\n```\na\n```\n".to_owned();
+        let result = get_test_cases(content.clone(), Options::default()).unwrap();
+        assert_eq!(result[0].name, "~~Old~~ New");
+        let config = Config {
+            constructs: Constructs::gfm(),
+            ..Default::default()
+        };
+        let result = get_test_cases_with_config(content, Options::default(), &config).unwrap();
+        assert_eq!(result[0].name, "Old New");
+    }
+
+    #[test]
+    fn test_iter() {
+        let path = PathBuf::from_iter([env!("CARGO_MANIFEST_DIR"), "src", "test.md"]);
+        let content = std::fs::read_to_string(path).unwrap();
+        let expected = get_test_cases(content.clone(), Options::default()).unwrap();
+        let result = iter_test_cases(content, Options::default())
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_iter_stops_after_error() {
+        let content =
+            "# A\n\n```\na\n```\n\n# B\n\n```toml options\nfoo = \n```\n\n# C\n\n```\nc\n```\n";
+        let mut iter = iter_test_cases(content.to_owned(), Options::default());
+        assert_eq!(iter.next().unwrap().unwrap().name, "A");
+        assert!(iter.next().unwrap().is_err());
+        assert_eq!(iter.next(), None);
+    }
+
+    #[test]
+    fn test_extractor() {
+        let extractor = TestCaseExtractor::new(Options::default())
+            .options_keyword("opts")
+            .enable_frontmatter(true);
+        let first = "+++\nfoo = 3\n+++\n\n# A\n\n```\na\n```\n".to_owned();
+        let second = "# B\n\n```toml opts\nbar = true\n```\n\n```\nb\n```\n".to_owned();
+        let first_result = extractor.clone().parse(first).unwrap();
+        let second_result = extractor.parse(second).unwrap();
+        assert_eq!(first_result[0].options, Options { foo: 3, bar: false });
+        assert_eq!(second_result[0].options, Options { foo: 0, bar: true });
+    }
+
+    #[test]
+    fn test_slugs() {
+        let content = [
+            "# Edge Cases (Weird!)",
+            "## Setup",
+            "```\na\n```",
+            "## Setup",
+            "```\nb\n```",
+        ]
+        .join("\n\n");
+        let result = get_test_cases(content, Options::default()).unwrap();
+        assert_eq!(result[0].heading_slugs, vec!["edge-cases-weird".to_owned()]);
+        assert_eq!(result[0].anchor, "setup");
+        assert_eq!(result[1].anchor, "setup-1");
+    }
+
+    #[test]
+    fn test_heading_depths() {
+        let content = [
+            "```\na\n```",
+            "# A",
+            "### B",
+            "```\nb\n```",
+            "## C",
+            "```\nc\n```",
+        ]
+        .join("\n\n");
+        let result = get_test_cases(content, Options::default()).unwrap();
+        let depths = result
+            .iter()
+            .map(|c| (c.heading_depths.clone(), c.depth))
+            .collect::<Vec<_>>();
+        assert_eq!(depths, vec![(vec![], 0), (vec![1], 3), (vec![1], 2)]);
+        assert_eq!(result[1].heading_lines, vec![5]);
+    }
+
+    #[test]
+    fn test_detect_duplicates() {
+        let content = ["# A", "## B", "```\na\n```", "# A", "## B", "```\nb\n```"].join("\n\n");
+        let result = get_test_cases(content.clone(), Options::default()).unwrap();
+        assert_eq!(result.len(), 2);
+        let result = TestCaseExtractor::new(Options::default())
+            .detect_duplicates(true)
+            .parse(content);
+        assert_eq!(
+            result,
+            Err(ParseError::DuplicateName {
+                name: "A > B".to_owned(),
+                first_line: 3,
+                second_line: 11,
+            })
+        );
+    }
+
+    #[test]
+    fn test_skip() {
+        let content = [
+            "# A <!-- skip -->",
+            "## B",
+            "```\n## C <!-- skip -->\n```",
+            "# D",
+            "```\nd\n```",
+        ]
+        .join("\n\n");
+        let result = get_test_cases(content, Options::default()).unwrap();
+        assert_eq!(result[0].headings, vec!["A".to_owned()]);
+        assert_eq!(result[0].name, "B");
+        assert!(result[0].skipped);
+        assert_eq!(result[0].args[0].value, "## C <!-- skip -->");
+        assert!(!result[1].skipped);
+    }
+
+    #[test]
+    fn test_focus() {
+        let content = [
+            "# A",
+            "```\na\n```",
+            "# B <!-- only -->",
+            "```\nb\n```",
+            "# C <!-- only --> <!-- skip -->",
+            "```\nc\n```",
+        ]
+        .join("\n\n");
+        let result = get_test_cases(content, Options::default()).unwrap();
+        let focused = TestCase::filter_focused(result);
+        assert_eq!(focused.len(), 2);
+        assert_eq!(focused[0].name, "B");
+        assert!(!focused[0].skipped);
+        assert_eq!(focused[1].name, "C");
+        assert!(focused[1].skipped);
+
+        let content = "# A\n\n```\na\n```\n".to_owned();
+        let result = get_test_cases(content, Options::default()).unwrap();
+        assert_eq!(TestCase::filter_focused(result).len(), 1);
+    }
+
+    #[test]
+    fn test_description() {
+        let content = [
+            "Ignored",
+            "# A",
+            "Checks that *apples* work.",
+            "Even [green](https://example.com) ones.",
+            "```\na\n```",
+            "Not part of the description.",
+            "```\nb\n```",
+            "# B",
+            "```\nc\n```",
+        ]
+        .join("\n\n");
+        let result = get_test_cases(content, Options::default()).unwrap();
+        assert_eq!(
+            result[0].description.as_deref(),
+            Some("Checks that apples work.\n\nEven green ones.")
+        );
+        assert_eq!(result[1].description, None);
+    }
+
+    #[test]
+    fn test_multiple_options_blocks() {
+        let content = [
+            "# A",
+            "```toml options\nfoo = 1\n```",
+            "```toml options\nbar = true\n```",
+            "```\na\n```",
+            "# B",
+            "```\nb\n```",
+            "```toml options\nfoo = 2\n```",
+        ]
+        .join("\n\n");
+        let result = get_test_cases(content, Options::default()).unwrap();
+        assert_eq!(result[0].options, Options { foo: 1, bar: true });
+        assert_eq!(result[1].options, Options { foo: 2, bar: false });
+    }
+
+    #[test]
+    fn test_from_path() {
+        let path = PathBuf::from_iter([env!("CARGO_MANIFEST_DIR"), "src", "test.md"]);
+        let content = std::fs::read_to_string(&path).unwrap();
+        let mut expected = get_test_cases(content, Options::default()).unwrap();
+        assert!(expected.iter().all(|c| c.source.is_none()));
+        for test_case in expected.iter_mut() {
+            test_case.source = Some(path.clone());
+        }
+        let result = test_cases_from_path(&path, Options::default()).unwrap();
+        assert_eq!(result, expected);
+
+        let missing = PathBuf::from_iter([env!("CARGO_MANIFEST_DIR"), "missing.md"]);
+        let result = test_cases_from_path(&missing, Options::default());
+        assert!(matches!(result, Err(ParseError::Io { path, .. }) if path == missing));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_round_trip() {
+        let path = PathBuf::from_iter([env!("CARGO_MANIFEST_DIR"), "src", "test.md"]);
+        let expected = test_cases_from_path(path, Options::default()).unwrap();
+        let json = serde_json::to_string(&expected).unwrap();
+        let result: Vec<TestCase<Options>> = serde_json::from_str(&json).unwrap();
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_path() {
+        let content = "```\na\n```\n\n# A\n\n## B\n\n```\nb\n```\n".to_owned();
+        let result = get_test_cases(content, Options::default()).unwrap();
+        assert_eq!(result[0].full_name(), "(Unnamed test)");
+        assert_eq!(result[1].full_name(), "A > B");
+        assert_eq!(result[1].path("/"), "A/B");
+    }
+
+    #[test]
+    fn test_list_items() {
+        let content = [
+            "# A",
+            "- one\n- `two`\n  - nested",
+            "```\nthree\n```",
+            "1. four",
+        ]
+        .join("\n\n");
+        let result = get_test_cases(content.clone(), Options::default()).unwrap();
+        assert_eq!(result[0].args.len(), 1);
+        let result = TestCaseExtractor::new(Options::default())
+            .collect_list_items(true)
+            .parse(content)
+            .unwrap();
+        let values = result[0]
+            .args
+            .iter()
+            .map(|a| a.value.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(values, vec!["one", "two", "three", "four"]);
+        assert_eq!(result[0].args[1].line, 4);
+    }
+
+    #[test]
+    fn test_tables() {
+        let table = "| input | expected |\n| - | - |\n| `a` | **b** |\n| c | d |";
+        let content = format!("# A\n\n{}\n\n```\ne\n```\n", table);
+        let result = get_test_cases(content.clone(), Options::default()).unwrap();
+        assert_eq!(result[0].args.len(), 1);
+        let result = TestCaseExtractor::new(Options::default())
+            .collect_tables(true)
+            .parse(content)
+            .unwrap();
+        let args = &result[0].args;
+        assert_eq!(args.len(), 2);
+        assert_eq!(args[0].value, table);
+        assert_eq!(args[0].line, 3);
+        assert_eq!(
+            args[0].table,
+            Some(ArgTable {
+                headers: vec!["input".to_owned(), "expected".to_owned()],
+                rows: vec![
+                    vec!["a".to_owned(), "b".to_owned()],
+                    vec!["c".to_owned(), "d".to_owned()],
+                ],
+            })
+        );
+        assert_eq!(args[1].table, None);
+    }
+
+    #[test]
+    fn test_include_empty() {
+        let content = [
+            "# A",
+            "Just prose.",
+            "# B",
+            "## C",
+            "```\nc\n```",
+            "## D",
+            "# E",
+        ]
+        .join("\n\n");
+        let result = get_test_cases(content.clone(), Options::default()).unwrap();
+        assert_eq!(result.len(), 1);
+        let result = TestCaseExtractor::new(Options::default())
+            .include_empty(true)
+            .parse(content)
+            .unwrap();
+        let names = result.iter().map(|c| c.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(names, vec!["A", "C", "D", "E"]);
+        assert!(result[0].args.is_empty());
+        assert_eq!(result[0].description.as_deref(), Some("Just prose."));
+    }
+
+    #[test]
+    fn test_clone_and_hash() {
+        let path = PathBuf::from_iter([env!("CARGO_MANIFEST_DIR"), "src", "test.md"]);
+        let result = test_cases_from_path(path, Options::default()).unwrap();
+        let mut set = result.iter().cloned().collect::<HashSet<_>>();
+        assert_eq!(set.len(), 3);
+        set.insert(result[0].clone());
+        assert_eq!(set.len(), 3);
+    }
+
+    #[test]
+    fn test_span() {
+        let content = "```\na\n```\n\n```\nb\n```\n\n# A\n\nProse\n".to_owned();
+        let result = TestCaseExtractor::new(Options::default())
+            .include_empty(true)
+            .parse(content)
+            .unwrap();
+        let expected = Span {
+            start_line: 1,
+            start_col: 1,
+            end_line: 7,
+            end_col: 4,
+        };
+        assert_eq!(result[0].span, expected);
+        let expected = Span {
+            start_line: 9,
+            start_col: 1,
+            end_line: 9,
+            end_col: 4,
+        };
+        assert_eq!(result[1].span, expected);
+    }
+
+    #[test]
+    fn test_options_reset() {
+        let content = [
+            "```toml options\nfoo = 1\nbar = true\n```",
+            "# A",
+            "```toml options reset\nfoo = 2\n```",
+            "```\na\n```",
+            "## B",
+            "```\nb\n```",
+            "# C",
+            "```\nc\n```",
+        ]
+        .join("\n\n");
+        let result = get_test_cases(content, Options::default()).unwrap();
+        assert_eq!(result[0].options, Options { foo: 2, bar: false });
+        assert_eq!(result[1].options, Options { foo: 2, bar: false });
+        assert_eq!(result[2].options, Options { foo: 1, bar: true });
+    }
+
+    #[derive(Default, PartialEq, Eq, Debug, Clone, Copy)]
+    struct Count(usize);
+
+    impl TryMergeSerialized for Count {
+        type Error = std::num::ParseIntError;
+
+        fn try_merge_serialized(&self, source: String) -> Result<Self, Self::Error> {
+            Ok(Count(self.0 + source.trim().parse::<usize>()?))
+        }
+    }
+
+    #[test]
+    fn test_try_merge_serialized() {
+        let content = "```txt options\n1\n```\n\n# A\n\n```txt options\n2\n```\n\n```\na\n```\n";
+        let result = get_test_cases(content.to_owned(), Count(0)).unwrap();
+        assert_eq!(result[0].options, Count(3));
+
+        let content = "# A\n\n```txt options\nx\n```\n".to_owned();
+        let result = get_test_cases(content, Count(0));
+        let Err(ParseError::OptionsMerge { message, .. }) = result else {
+            panic!("Expected an options error");
+        };
+        assert_eq!(message, "invalid digit found in string");
+    }
+
+    #[test]
+    fn test_code_without_position() {
+        let code = |value: &str, meta: Option<&str>| {
+            Node::Code(Code {
+                value: value.to_owned(),
+                position: None,
+                lang: Some("toml".to_owned()),
+                meta: meta.map(str::to_owned),
+            })
+        };
+        let mut iter = TestCaseIter::new(String::new(), Options::default(), Config::default());
+        iter.nodes = vec![code("foo = ", Some("options")), code("a", None)].into_iter();
+        let result = iter.collect::<Result<Vec<_>, _>>();
+        assert!(matches!(
+            result,
+            Err(ParseError::OptionsMerge { line: 0, .. })
+        ));
+
+        let mut iter = TestCaseIter::new(String::new(), Options::default(), Config::default());
+        iter.nodes = vec![code("a", None)].into_iter();
+        let result = iter.collect::<Result<Vec<_>, _>>().unwrap();
+        assert_eq!(result[0].args[0].line, 0);
+    }
+
+    #[test]
+    fn test_attributes() {
+        let content = [
+            "# A",
+            "<!-- tag: slow; timeout: 30 -->",
+            "<!-- owner : me -->",
+            "```\n<!-- tag: fast -->\n```",
+            "# B",
+            "```\nb\n```",
+        ]
+        .join("\n\n");
+        let result = get_test_cases(content, Options::default()).unwrap();
+        let expected = BTreeMap::from([
+            ("tag".to_owned(), "slow".to_owned()),
+            ("timeout".to_owned(), "30".to_owned()),
+            ("owner".to_owned(), "me".to_owned()),
+        ]);
+        assert_eq!(result[0].attributes, expected);
+        assert!(result[1].attributes.is_empty());
+    }
+
+    #[test]
+    fn test_setext_headings() {
+        let content = [
+            "Suite\n=====",
+            "## Group",
+            "### Case",
+            "```\na\n```",
+            "Other group\n-----------",
+            "```\nb\n```",
+            "### Nested",
+            "```\nc\n```",
+        ]
+        .join("\n\n");
+        let result = get_test_cases(content, Options::default()).unwrap();
+        let paths = result.iter().map(|c| c.full_name()).collect::<Vec<_>>();
+        assert_eq!(
+            paths,
+            vec![
+                "Suite > Group > Case",
+                "Suite > Other group",
+                "Suite > Other group > Nested"
+            ]
+        );
+        let lines = result.iter().map(|c| c.line_number).collect::<Vec<_>>();
+        assert_eq!(lines, vec![6, 12, 19]);
+    }
+
+    #[test]
+    fn test_arg_lang() {
+        let content = "# A\n\n```sql\nSELECT 1\n```\n\n```json expected\n1\n```\n".to_owned();
+        let result = get_test_cases(content, Options::default()).unwrap();
+        let args = &result[0].args;
+        assert_eq!(args[0].lang.as_deref(), Some("sql"));
+        assert_eq!(args[0].meta, None);
+        assert_eq!(args[1].lang.as_deref(), Some("json"));
+        assert_eq!(args[1].meta.as_deref(), Some("expected"));
+    }
+
+    #[test]
+    fn test_arg_roles() {
+        let content = [
+            "# A",
+            "```sql\nSELECT 1\n```",
+            "```json expected\n1\n```",
+            "```expected\n2\n```",
+            "```\n3\n```",
+        ]
+        .join("\n\n");
+        let result = get_test_cases(content, Options::default()).unwrap();
+        let roles = result[0].args.iter().map(|a| a.role).collect::<Vec<_>>();
+        assert_eq!(
+            roles,
+            vec![
+                ArgRole::Input,
+                ArgRole::Expected,
+                ArgRole::Expected,
+                ArgRole::Input
+            ]
+        );
+        let inputs = result[0]
+            .inputs()
+            .map(|a| a.value.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(inputs, vec!["SELECT 1", "3"]);
+        assert_eq!(result[0].expected().map(|a| a.value.as_str()), Some("1"));
+    }
+
+    #[test]
+    fn test_setup() {
+        let content = [
+            "```setup\nroot\n```",
+            "# A",
+            "```sql setup\na\n```",
+            "## B",
+            "```\nb\n```",
+            "```setup\nb\n```",
+            "## C",
+            "```\nc\n```",
+            "# D",
+            "```\nd\n```",
+        ]
+        .join("\n\n");
+        let result = get_test_cases(content, Options::default()).unwrap();
+        let setups = result.iter().map(|c| c.setup.clone()).collect::<Vec<_>>();
+        assert_eq!(
+            setups,
+            vec![
+                vec!["root".to_owned(), "a".to_owned(), "b".to_owned()],
+                vec!["root".to_owned(), "a".to_owned()],
+                vec!["root".to_owned()],
+            ]
+        );
+        assert_eq!(result[0].args, vec![arg("b", 13)]);
+    }
+
+    #[test]
+    fn test_trim_args() {
+        let content = "# A\n\n```\n  a  \n\n```\n".to_owned();
+        let values = [
+            (ArgTrim::None, "  a  \n"),
+            (ArgTrim::TrailingNewline, "  a  "),
+            (ArgTrim::Full, "a"),
+        ];
+        for (trim, value) in values {
+            let result = TestCaseExtractor::new(Options::default())
+                .trim_args(trim)
+                .parse(content.clone())
+                .unwrap();
+            assert_eq!(result[0].args[0].value, value);
+        }
+    }
+
+    #[test]
+    fn test_options_trace() {
+        let content = [
+            "# A",
+            "```toml options\nfoo = 1\n```",
+            "## B",
+            "```toml options reset\nbar = true\n```",
+            "```\nb\n```",
+            "# C",
+            "```\nc\n```",
+        ]
+        .join("\n\n");
+        let result = get_test_cases(content, Options::default()).unwrap();
+        assert_eq!(result[0].options_trace, vec![(9, "bar = true".to_owned())]);
+        assert_eq!(result[1].options_trace, vec![]);
+    }
+
+    #[test]
+    fn test_unnamed_name() {
+        let content = "```\na\n```\n".to_owned();
+        let result = get_test_cases(content.clone(), Options::default()).unwrap();
+        assert_eq!(result[0].name, "(Unnamed test)");
+        let result = TestCaseExtractor::new(Options::default())
+            .unnamed_name("Top")
+            .parse(content)
+            .unwrap();
+        assert_eq!(result[0].name, "Top");
+    }
+
+    #[test]
+    fn test_split_on_thematic_break() {
+        let content = [
+            "# A",
+            "```\na\n```",
+            "---",
+            "Second case.",
+            "```\nb\n```",
+            "---",
+            "# C",
+            "```\nc\n```",
+        ]
+        .join("\n\n");
+        let result = get_test_cases(content.clone(), Options::default()).unwrap();
+        assert_eq!(result.len(), 2);
+        let result = TestCaseExtractor::new(Options::default())
+            .split_on_thematic_break(true)
+            .parse(content)
+            .unwrap();
+        let cases = result
+            .iter()
+            .map(|c| (c.name.as_str(), c.args.clone(), c.description.as_deref()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            cases,
+            vec![
+                ("A", vec![arg("a", 3)], None),
+                ("A", vec![arg("b", 11)], Some("Second case.")),
+                ("C", vec![arg("c", 19)], None),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_split_without_duplicates() {
+        let content = "# A\n\n```\na\n```\n\n---\n\n```\nb\n```\n".to_owned();
+        let result = TestCaseExtractor::new(Options::default())
+            .split_on_thematic_break(true)
+            .detect_duplicates(true)
+            .parse(content);
+        assert_eq!(result.unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_visit() {
+        let content = "# A\n\n```\na\n```\n\n# B\n\n```\nb\n```\n".to_owned();
+        let mut names = vec![];
+        visit_test_cases(content.clone(), Options::default(), |c| names.push(c.name)).unwrap();
+        assert_eq!(names, vec!["A".to_owned(), "B".to_owned()]);
+
+        let content = "# A\n\n```\na\n```\n\n# ![x](y)\n".to_owned();
+        let mut names = vec![];
+        let result = visit_test_cases(content, Options::default(), |c| names.push(c.name));
+        assert_eq!(result, Err(ParseError::NonTextHeading { line: 7 }));
+        assert_eq!(names, vec!["A".to_owned()]);
+    }
+
+    #[test]
+    fn test_ignore_indented_code() {
+        let content = ["# A", "```\nfenced\n```", "    indented", "~~~\ntilde\n~~~"].join("\n\n");
+        let result = get_test_cases(content.clone(), Options::default()).unwrap();
+        assert_eq!(
+            result[0].args,
+            vec![arg("fenced", 3), arg("indented", 7), arg("tilde", 9)]
+        );
+        let result = TestCaseExtractor::new(Options::default())
+            .ignore_indented_code(true)
+            .parse(content)
+            .unwrap();
+        assert_eq!(result[0].args, vec![arg("fenced", 3), arg("tilde", 9)]);
+    }
+
+    #[test]
+    fn test_options_meta_tokens() {
+        let content = [
+            "# A",
+            "```toml title=\"Shared\" options\nfoo = 1\n```",
+            "```toml options reset linenos\nbar = true\n```",
+            "```toml optionsy\nfoo = 2\n```",
+        ]
+        .join("\n\n");
+        let result = get_test_cases(content, Options::default()).unwrap();
+        assert_eq!(result[0].options, Options { foo: 0, bar: true });
+        assert_eq!(result[0].args[0].value, "foo = 2");
+    }
+
+    #[test]
+    fn test_options_by_lang() {
+        let content = [
+            "# A",
+            "```toml options\nfoo = 1\n```",
+            "```options reset\nbar = true\n```",
+            "```\na\n```",
+        ]
+        .join("\n\n");
+        let result = get_test_cases(content.clone(), Options::default()).unwrap();
+        assert_eq!(result[0].options, Options { foo: 1, bar: false });
+        assert_eq!(result[0].args.len(), 2);
+        assert_eq!(result[0].args[0].lang.as_deref(), Some("options"));
+        let result = TestCaseExtractor::new(Options::default())
+            .options_by_lang(true)
+            .parse(content)
+            .unwrap();
+        assert_eq!(result[0].options, Options { foo: 0, bar: true });
+        assert_eq!(result[0].args, vec![arg("a", 11)]);
+    }
+
+    #[test]
+    fn test_validate() {
+        let content = "# A\n\n```\na\n```\n\n# B\n\n```\nb\n```\n".to_owned();
+        assert_eq!(validate(content, Options::default()), Ok(2));
+        let content = "# A\n\n```\na\n```\n\n# B\n\n```toml options\nfoo = \n```\n".to_owned();
+        assert!(matches!(
+            validate(content, Options::default()),
+            Err(ParseError::OptionsMerge {
+                line: 9,
+                end_line: 11,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_max_heading_depth() {
+        let content = [
+            "# A",
+            "### B",
+            "```\nb\n```",
+            "#### Notes",
+            "```\nnotes\n```",
+            "## C",
+            "```\nc\n```",
+        ]
+        .join("\n\n");
+        let result = TestCaseExtractor::new(Options::default())
+            .max_heading_depth(3)
+            .parse(content)
+            .unwrap();
+        let cases = result
+            .iter()
+            .map(|c| (c.full_name(), c.args.len()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            cases,
+            vec![("A > B".to_owned(), 2), ("A > C".to_owned(), 1)]
+        );
+    }
+
+    #[test]
+    fn test_columns() {
+        let content = "```\na\n```\n\n# B\n\n```\nb\n```\n".to_owned();
+        let result = get_test_cases(content, Options::default()).unwrap();
+        assert_eq!(result[0].column, 0);
+        assert_eq!(result[1].column, 1);
+        assert_eq!((result[1].args[0].line, result[1].args[0].column), (7, 1));
+
+        let mut iter = TestCaseIter::new(String::new(), Options::default(), Config::default());
+        iter.push_arg(arg("a", 0), None);
+        assert_eq!(iter.args[0].column, 0);
+    }
+
+    #[test]
+    fn test_tags() {
+        let content = [
+            "# Integration <!-- tags: network -->",
+            "## A <!-- tags: slow, network -->",
+            "```\na\n```",
+            "## B",
+            "<!-- tags: flaky -->",
+            "```\nb\n```",
+            "### C",
+            "```\nc\n```",
+            "# D",
+            "```\nd\n```",
+        ]
+        .join("\n\n");
+        let result = get_test_cases(content, Options::default()).unwrap();
+        let tags = result.iter().map(|c| c.tags.join(",")).collect::<Vec<_>>();
+        assert_eq!(
+            tags,
+            vec!["network,slow", "network,flaky", "network,flaky", ""]
+        );
+        assert_eq!(result[1].attributes["tags"], "flaky");
+    }
+
+    #[test]
+    fn test_options_file() {
+        let dir = std::env::temp_dir().join("testcase-markdown-options-file");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("spec")).unwrap();
+        std::fs::write(dir.join("spec/defaults.toml"), "foo = 1\nbar = true").unwrap();
+        let content = [
+            "# A",
+            "```toml options file=\"defaults.toml\"\nfoo = 2\n```",
+            "```\na\n```",
+            "# B",
+            "```toml options file=\"./defaults.toml\"\n```",
+            "```\nb\n```",
+            "# C",
+            "```toml options file=\"missing.toml\"\n```",
+        ]
+        .join("\n\n");
+        let path = dir.join("spec/test.md");
+        std::fs::write(&path, content).unwrap();
+        let extractor = TestCaseExtractor::new(Options::default()).options_files(true);
+        let result = extractor.parse_path(&path);
+        let Err(ParseError::InFile { error, .. }) = result else {
+            panic!("Expected an error for the missing options file");
+        };
+        let missing = dir.join("spec/missing.toml");
+        assert!(matches!(&*error, ParseError::Io { path, .. } if *path == missing));
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        let content = content.split("# C").next().unwrap().to_owned();
+        std::fs::write(&path, content).unwrap();
+        let result = extractor.parse_path(&path).unwrap();
+        assert_eq!(result[0].options, Options { foo: 2, bar: true });
+        assert_eq!(result[1].options, Options { foo: 1, bar: true });
+
+        // Without `options_files`, the file is ignored.
+        let result = test_cases_from_path(&path, Options::default()).unwrap();
+        assert_eq!(result[0].options, Options { foo: 2, bar: false });
+        assert_eq!(result[1].options, Options::default());
+    }
+
+    #[test]
+    fn test_options_file_outside_dir() {
+        let dir = std::env::temp_dir().join("testcase-markdown-options-file-outside");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("spec")).unwrap();
+        std::fs::write(dir.join("secret.toml"), "foo = 1").unwrap();
+        let extractor = TestCaseExtractor::new(Options::default()).options_files(true);
+        let path = dir.join("spec/test.md");
+        for file in ["../secret.toml", "a/../../secret.toml", "/etc/hosts"] {
+            let content = format!("# A\n\n```toml options file=\"{}\"\n```\n", file);
+            std::fs::write(&path, &content).unwrap();
+            let Err(ParseError::InFile { error, .. }) = extractor.parse_path(&path) else {
+                panic!("Expected an error for {}", file);
+            };
+            assert_eq!(
+                *error,
+                ParseError::OptionsFile {
+                    line: 3,
+                    path: file.to_owned()
+                }
+            );
+        }
+        // Without a path to resolve against, no file may be read.
+        let result = extractor.parse("# A\n\n```toml options file=\"a.toml\"\n```\n".to_owned());
+        assert!(matches!(
+            result,
+            Err(ParseError::OptionsFile { line: 3, .. })
+        ));
+
+        std::fs::write(dir.join("spec/bad.toml"), "foo = ").unwrap();
+        let content = "# A\n\n```toml options file=\"bad.toml\"\n```\n";
+        std::fs::write(&path, content).unwrap();
+        let Err(ParseError::InFile { error, .. }) = extractor.parse_path(&path) else {
+            panic!("Expected an error for the bad options file");
+        };
+        assert!(matches!(&*error, ParseError::OptionsMerge { source, .. } if source.is_empty()));
+    }
+
+    #[test]
+    fn test_strict() {
+        let content = [
+            "# A",
+            "```toml options reset\nfoo = 1\n```",
+            "```rust\na\n```",
+            "```json expected\nb\n```",
+            "```toml optins\nfoo = 2\n```",
+        ]
+        .join("\n\n");
+        let result = get_test_cases(content.clone(), Options::default()).unwrap();
+        assert_eq!(result[0].args.len(), 3);
+        let result = TestCaseExtractor::new(Options::default())
+            .strict(true)
+            .parse(content);
+        assert_eq!(
+            result,
+            Err(ParseError::UnknownMeta {
+                line: 15,
+                meta: "optins".to_owned()
+            })
+        );
+    }
+
+    #[test]
+    fn test_semantic_eq() {
+        let content = "# A\n\n```toml options\nfoo = 1\n```\n\n```\na\n```\n".to_owned();
+        let reformatted = "\n\n# A\n```toml options\nfoo = 1\n```\n```\na\n```\n".to_owned();
+        let changed = "# A\n\n```toml options\nfoo = 1\n```\n\n```\nb\n```\n".to_owned();
+        let original = get_test_cases(content, Options::default()).unwrap();
+        let reformatted = get_test_cases(reformatted, Options::default()).unwrap();
+        let changed = get_test_cases(changed, Options::default()).unwrap();
+        assert_ne!(original, reformatted);
+        assert!(original[0].semantic_eq(&reformatted[0]));
+        assert!(!original[0].semantic_eq(&changed[0]));
+    }
+
+    #[test]
+    fn test_literal_options() {
+        let content = [
+            "# A",
+            "```toml options\nfoo = 1\n```",
+            "```toml options literal\nfoo = 2\n```",
+        ]
+        .join("\n\n");
+        let result = TestCaseExtractor::new(Options::default())
+            .strict(true)
+            .parse(content)
+            .unwrap();
+        assert_eq!(result[0].options.foo, 1);
+        let arg = &result[0].args[0];
+        assert_eq!(arg.value, "foo = 2");
+        assert_eq!(arg.meta.as_deref(), Some("options literal"));
+    }
+
+    #[test]
+    fn test_literal_setup() {
+        let content = [
+            "# A",
+            "```setup\na\n```",
+            "```setup literal\nb\n```",
+            "```sql setup literal\nc\n```",
+        ]
+        .join("\n\n");
+        let result = get_test_cases(content, Options::default()).unwrap();
+        assert_eq!(result[0].setup, vec!["a".to_owned()]);
+        let args = result[0]
+            .args
+            .iter()
+            .map(|a| (a.lang.as_deref(), a.value.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(args, vec![(Some("setup"), "b"), (Some("sql"), "c")]);
+    }
+
+    #[test]
+    fn test_merge_options() {
+        let base = Options { foo: 1, bar: true };
+        assert_eq!(
+            merge_options(&base, "foo = 2".to_owned()),
+            Ok(Options { foo: 2, bar: true })
+        );
+        assert!(merge_options(&base, "foo = ".to_owned()).is_err());
+    }
+
+    #[test]
+    fn test_options_scope() {
+        let content = [
+            "+++\nbar = true\n+++",
+            "```toml options\nfoo = 1\n```",
+            "# A",
+            "```toml options\nfoo = 2\n```",
+            "## B",
+            "```\nb\n```",
+            "```toml options\nfoo = 3\n```",
+            "## C",
+            "```\nc\n```",
+            "# D",
+            "```\nd\n```",
+        ]
+        .join("\n\n");
+        let options = |scope: bool| {
+            TestCaseExtractor::new(Options::default())
+                .enable_frontmatter(true)
+                .scope_options_to_section(scope)
+                .parse(content.clone())
+                .unwrap()
+                .iter()
+                .map(|c| (c.name.clone(), c.options.foo, c.options.bar))
+                .collect::<Vec<_>>()
+        };
+        // Either way, siblings are isolated from each other but inherit from
+        // their ancestors. By default, that includes options blocks before the
+        // first heading.
+        assert_eq!(
+            options(false),
+            vec![
+                ("B".to_owned(), 3, true),
+                ("C".to_owned(), 2, true),
+                ("D".to_owned(), 1, true),
+            ]
+        );
+        assert_eq!(
+            options(true),
+            vec![
+                ("B".to_owned(), 3, true),
+                ("C".to_owned(), 2, true),
+                ("D".to_owned(), 0, true),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_iter_is_send() {
+        fn assert_send<T: Send>(_: T) {}
+        assert_send(iter_test_cases(String::new(), Options::default()));
+    }
+
+    #[test]
+    fn test_intra_block_delimiter() {
+        let content = "# A\n\n```sql\na\n---\nb\nc\n---\nd\n```\n".to_owned();
+        let result = get_test_cases(content.clone(), Options::default()).unwrap();
+        assert_eq!(result[0].args.len(), 1);
+        let result = TestCaseExtractor::new(Options::default())
+            .intra_block_delimiter("---")
+            .parse(content)
+            .unwrap();
+        let args = result[0]
+            .args
+            .iter()
+            .map(|a| (a.value.as_str(), a.line, a.lang.as_deref()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            args,
+            vec![
+                ("a", 3, Some("sql")),
+                ("b\nc", 5, Some("sql")),
+                ("d", 8, Some("sql"))
+            ]
+        );
+    }
+
+    #[test]
+    fn test_byte_range() {
+        let content = "```\na\n```\n\n# B\n\n```\nb\n```\n\n# C\n".to_owned();
+        let result = TestCaseExtractor::new(Options::default())
+            .include_empty(true)
+            .parse(content.clone())
+            .unwrap();
+        let slices = result
+            .iter()
+            .map(|c| &content[c.byte_range.clone().unwrap()])
+            .collect::<Vec<_>>();
+        assert_eq!(slices, vec!["```\na\n```", "# B\n\n```\nb\n```", "# C"]);
+    }
+
+    #[test]
+    fn test_options_keyword_matching() {
+        let content =
+            "# A\n\n```toml Options\nfoo = 1\n```\n\n```toml opt\nfoo = 2\n```\n".to_owned();
+        let result = get_test_cases(content.clone(), Options::default()).unwrap();
+        assert_eq!(result[0].args.len(), 2);
+        let result = TestCaseExtractor::new(Options::default())
+            .options_keyword_case_insensitive(true)
+            .parse(content.clone())
+            .unwrap();
+        assert_eq!(result[0].options.foo, 1);
+        assert_eq!(result[0].args.len(), 1);
+
+        #[cfg(feature = "regex")]
+        {
+            let pattern = regex::Regex::new("^opt(ions)?$").unwrap();
+            let result = TestCaseExtractor::new(Options::default())
+                .options_keyword_case_insensitive(true)
+                .options_keyword_pattern(pattern)
+                .parse(content)
+                .unwrap();
+            assert_eq!(result[0].options.foo, 2);
+            assert_eq!(result[0].args[0].value, "foo = 1");
+        }
+    }
+
+    #[test]
+    fn test_warnings() {
+        let content = [
+            "```toml options\nfoo = 1\n```",
+            "# A",
+            "```toml options\nfoo = 2\n```",
+            "## B",
+            "```\nb\n```",
+            "# C",
+            "```toml options\nfoo = 3\n```",
+            "## D",
+            "#",
+            "```\ne\n```",
+            "# A",
+            "## B",
+            "```\nb\n```",
+            "# F",
+            "```toml options\nfoo = 4\n```",
+        ]
+        .join("\n\n");
+        let (result, warnings) = get_test_cases_with_warnings(content, Options::default()).unwrap();
+        assert_eq!(result.len(), 3);
+        assert_eq!(
+            warnings,
+            vec![
+                Warning::UnusedOptions { line: 19 },
+                Warning::EmptyHeading { line: 25 },
+                Warning::DuplicateName {
+                    name: "A > B".to_owned(),
+                    first_line: 11,
+                    second_line: 33,
+                },
+                Warning::UnusedOptions { line: 41 },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_options_inheritance_matrix() {
+        let content = [
+            "```toml options\nfoo = 1\n```",
+            "## A",
+            "```toml options\nfoo = 2\n```",
+            "### A1",
+            "```toml options\nbar = true\n```",
+            "```\na1\n```",
+            "### A2",
+            "```\na2\n```",
+            "## B",
+            "```\nb\n```",
+            "### B1",
+            "```\nb1\n```",
+            "#### B1a",
+            "```toml options\nfoo = 3\n```",
+            "```\nb1a\n```",
+            "### B2",
+            "```\nb2\n```",
+            "# C",
+            "```\nc\n```",
+        ]
+        .join("\n\n");
+        let result = get_test_cases(content, Options::default()).unwrap();
+        let options = result
+            .iter()
+            .map(|c| (c.name.as_str(), c.options.foo, c.options.bar))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            options,
+            vec![
+                // Descendants inherit from their whole ancestor chain.
+                ("A1", 2, true),
+                // Siblings don't see each other's options.
+                ("A2", 2, false),
+                // A sibling of an ancestor only inherits from the root.
+                ("B", 1, false),
+                // Descendants of that sibling inherit from it, not from A.
+                ("B1", 1, false),
+                ("B1a", 3, false),
+                // Options from a deeper section end with it.
+                ("B2", 1, false),
+                // A shallower heading only inherits from the root.
+                ("C", 1, false),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_chunk_test_cases() {
+        let cases = (0..5)
+            .map(|i| TestCase {
+                name: i.to_string(),
+                ..Default::default()
+            })
+            .collect::<Vec<TestCase<Options>>>();
+        let sizes = |size: usize| {
+            chunk_test_cases(cases.clone(), size)
+                .iter()
+                .map(|chunk| chunk.len())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(sizes(2), vec![2, 2, 1]);
+        assert_eq!(sizes(5), vec![5]);
+        assert_eq!(sizes(10), vec![5]);
+        assert_eq!(sizes(0), vec![5]);
+        assert_eq!(chunk_test_cases(cases, 2)[2][0].name, "4");
+        assert!(chunk_test_cases::<Options>(vec![], 2).is_empty());
+    }
+
+    #[test]
+    fn test_continue_on_error() {
+        let content = [
+            "# A",
+            "```toml options\nfoo = 1\n```",
+            "## B",
+            "```toml options\nfoo = \n```",
+            "```\nb\n```",
+            "## C",
+            "```\nc\n```",
+        ]
+        .join("\n\n");
+        let result = get_test_cases(content.clone(), Options::default());
+        assert!(matches!(
+            result,
+            Err(ParseError::OptionsMerge { line: 9, .. })
+        ));
+        let (result, warnings) = TestCaseExtractor::new(Options::default())
+            .continue_on_error(true)
+            .parse_with_warnings(content)
+            .unwrap();
+        assert_eq!(result.len(), 2);
+        assert_eq!(result[0].options.foo, 1);
+        assert_eq!(result[0].options_trace.len(), 1);
+        let [Warning::OptionsMerge { line, message }] = &warnings[..] else {
+            panic!("Expected an options warning, got {:?}", warnings);
+        };
+        assert_eq!(*line, 9);
+        assert!(!message.is_empty());
+    }
+
+    #[test]
+    fn test_name_annotation() {
+        let content = [
+            "# Fruits <!-- name: fruits -->",
+            "## Apple (Granny Smith) <!-- name: apple_granny -->",
+            "```\na\n```",
+            "## Pear",
+            "```\nb\n```",
+        ]
+        .join("\n\n");
+        let result = get_test_cases(content, Options::default()).unwrap();
+        assert_eq!(result[0].name, "apple_granny");
+        assert_eq!(result[0].title, "Apple (Granny Smith)");
+        assert_eq!(result[0].anchor, "apple-granny-smith");
+        assert_eq!(result[0].headings, vec!["fruits"]);
+        assert_eq!(result[1].name, "Pear");
+        assert_eq!(result[1].title, "Pear");
+    }
+
+    #[test]
+    fn test_display() {
+        let content = ["```\na\n```", "# A", "## B", "```\nb\n```\n\n```\nc\n```"].join("\n\n");
+        let result = get_test_cases(content, Options::default()).unwrap();
+        assert_eq!(result[0].to_string(), "(Unnamed test) (line 1, 1 arg)");
+        assert_eq!(result[1].to_string(), "A > B (line 7, 2 args)");
+    }
+
+    #[test]
+    fn test_to_markdown() {
+        let path = PathBuf::from_iter([env!("CARGO_MANIFEST_DIR"), "src", "test.md"]);
+        let content = std::fs::read_to_string(path).unwrap();
+        let content = [
+            "```toml options\nfoo = 2\n```",
+            "```\nroot\n```",
+            &content,
+            "## Nested <!-- name: nested -->",
+            "```toml options\nbar = true\n```",
+            "<!-- arg: query -->",
+            "````sql expected\n```\n````",
+            "### Child",
+            "```\n```",
+        ]
+        .join("\n\n");
+        let cases = get_test_cases(content, Options::default()).unwrap();
+        let markdown = to_markdown(&cases);
+        let result = get_test_cases(markdown.clone(), Options::default()).unwrap();
+        assert_eq!(result.len(), cases.len());
+        for (a, b) in result.iter().zip(&cases) {
+            assert!(a.semantic_eq(b), "{:?} != {:?}", a, b);
+        }
+        assert!(markdown.starts_with("```toml options\nfoo = 2\n```\n\n```\nroot\n```"));
+        assert!(markdown.contains("\n### Pear\n\n```toml options\nfoo = 5\n```\n"));
+        assert!(markdown.contains("\n## Nested <!-- name: nested -->\n"));
+    }
+
+    #[test]
+    fn test_merge_child_args() {
+        let content = [
+            "```\nroot\n```",
+            "# Parse",
+            "## Numbers",
+            "```\n1 + 2\n```",
+            "### Expected",
+            "```\n3\n```",
+            "#### Notes",
+            "```\nnote\n```",
+            "## Strings",
+            "```\n\"a\"\n```",
+            "# Other",
+            "## Empty",
+            "### Child",
+            "```\nc\n```",
+        ]
+        .join("\n\n");
+        let result = TestCaseExtractor::new(Options::default())
+            .merge_child_args(true)
+            .parse(content)
+            .unwrap();
+        let summary = result
+            .iter()
+            .map(|c| {
+                let values = c.args.iter().map(|a| a.value.as_str()).collect::<Vec<_>>();
+                (c.full_name(), values)
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            summary,
+            vec![
+                ("(Unnamed test)".to_owned(), vec!["root"]),
+                ("Parse > Numbers".to_owned(), vec!["1 + 2", "3", "note"]),
+                ("Parse > Strings".to_owned(), vec!["\"a\""]),
+                ("Other > Empty > Child".to_owned(), vec!["c"]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_arg_labels() {
+        let content = [
+            "# A",
+            "<!-- arg: query -->",
+            "```sql\nselect 1\n```",
+            "```\nunlabeled\n```",
+            "<!-- arg: query -->\n<!-- tag: slow -->",
+            "```\nagain\n```",
+            "<!-- arg: params -->",
+            "Some prose",
+            "```\nalso unlabeled\n```",
+        ]
+        .join("\n\n");
+        let result = get_test_cases(content, Options::default()).unwrap();
+        let labels = result[0]
+            .args
+            .iter()
+            .map(|a| a.label.as_deref())
+            .collect::<Vec<_>>();
+        assert_eq!(labels, vec![Some("query"), None, Some("query"), None]);
+        let values = result[0]
+            .args_by_label("query")
+            .map(|a| a.value.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(values, vec!["select 1", "again"]);
+        let first = result[0].arg_by_label("query").map(|a| a.value.as_str());
+        assert_eq!(first, Some("select 1"));
+        assert_eq!(result[0].arg_by_label("params"), None);
+        assert_eq!(result[0].attributes.len(), 1);
+    }
+
+    #[test]
+    fn test_default_arg_lang() {
+        let content = "# A\n\n```\na\n```\n\n```json\nb\n```\n\n```expected\nc\n```".to_owned();
+        let result = TestCaseExtractor::new(Options::default())
+            .default_arg_lang("sql")
+            .parse(content)
+            .unwrap();
+        let langs = result[0]
+            .args
+            .iter()
+            .map(|a| a.lang.as_deref())
+            .collect::<Vec<_>>();
+        assert_eq!(langs, vec![Some("sql"), Some("json"), Some("expected")]);
+    }
+
+    #[test]
+    fn test_crlf() {
+        let content = "# A\r\n\r\n```\r\none\r\ntwo\r\n```\r\n\r\n## B\r\n\r\n- x\r\n\r\n```\r\nthree\r\n---\r\nfour\r\n```\r\n";
+        let result = TestCaseExtractor::new(Options::default())
+            .intra_block_delimiter("---")
+            .parse(content.to_owned())
+            .unwrap();
+        assert_eq!(result[0].line_number, 1);
+        assert_eq!(result[0].args, vec![arg("one\ntwo", 3)]);
+        assert_eq!(result[1].line_number, 8);
+        assert_eq!(result[1].args, vec![arg("three", 12), arg("four", 14)]);
+        let result = TestCaseExtractor::new(Options::default())
+            .normalize_newlines(false)
+            .parse(content.to_owned())
+            .unwrap();
+        assert_eq!(result[0].args[0].value, "one\r\ntwo");
+    }
+
+    #[test]
+    fn test_get_test_cases_limited() {
+        let content = [
+            "```toml options\nfoo = 1\n```",
+            "# A",
+            "```toml options\nbar = true\n```",
+            "```\na\n```",
+            "# B",
+            "```\nb\n```",
+            "# C",
+            "```toml options\nfoo = \n```",
+            "```\nc\n```",
+        ]
+        .join("\n\n");
+        let result = get_test_cases_limited(content.clone(), Options::default(), 2).unwrap();
+        let names = result.iter().map(|c| c.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(names, vec!["A", "B"]);
+        assert_eq!(result[0].options, Options { foo: 1, bar: true });
+        assert_eq!(result[1].options, Options { foo: 1, bar: false });
+        assert!(get_test_cases_limited(content, Options::default(), 3).is_err());
+    }
+
+    #[cfg(feature = "render_html")]
+    #[test]
+    fn test_render_html() {
+        let content = [
+            "# A",
+            "```sql\nselect 1 < 2\n```",
+            "| a | b |\n| - | - |\n| 1 | 2 |",
+            "- item",
+        ]
+        .join("\n\n");
+        let result = TestCaseExtractor::new(Options::default())
+            .collect_tables(true)
+            .collect_list_items(true)
+            .parse(content)
+            .unwrap();
+        let html = result[0]
+            .args
+            .iter()
+            .map(|a| a.html.as_deref().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            html[0],
+            "<pre><code class=\"language-sql\">select 1 &lt; 2\n</code></pre>"
+        );
+        assert!(html[1].starts_with("<table>"));
+        assert_eq!(html[2], "<p>item</p>");
+    }
+
+    #[test]
+    fn test_options_apply_to_section() {
+        let content = [
+            "# A",
+            "```\na\n```",
+            "---",
+            "```\nb\n```",
+            "```toml options\nfoo = 3\n```",
+            "# B",
+            "```\nc\n```",
+            "---",
+            "```\nd\n```",
+            "---",
+            "```toml options\nbar = true\n```",
+        ]
+        .join("\n\n");
+        let foos = |apply_to_section| {
+            TestCaseExtractor::new(Options::default())
+                .split_on_thematic_break(true)
+                .options_apply_to_section(apply_to_section)
+                .parse_with_warnings(content.clone())
+                .unwrap()
+        };
+        let (result, warnings) = foos(false);
+        let options = result.iter().map(|c| c.options).collect::<Vec<_>>();
+        assert_eq!(
+            options,
+            vec![
+                Options { foo: 0, bar: false },
+                Options { foo: 3, bar: false },
+                Options { foo: 0, bar: false },
+                Options { foo: 0, bar: false },
+            ]
+        );
+        assert_eq!(warnings, vec![Warning::UnusedOptions { line: 31 }]);
+        let (result, warnings) = foos(true);
+        let options = result.iter().map(|c| c.options).collect::<Vec<_>>();
+        assert_eq!(
+            options,
+            vec![
+                Options { foo: 3, bar: false },
+                Options { foo: 3, bar: false },
+                Options { foo: 0, bar: true },
+                Options { foo: 0, bar: true },
+            ]
+        );
+        assert_eq!(result[0].options_trace, vec![(13, "foo = 3".to_owned())]);
+        assert!(warnings.is_empty());
+    }
+
+    #[test]
+    fn test_options_inherited() {
+        let content = [
+            "```\nroot\n```",
+            "# A",
+            "```toml options\nfoo = 1\n```",
+            "```\na\n```",
+            "## B",
+            "```\nb\n```",
+            "## C",
+            "```toml options\nfoo = 1\n```",
+            "```\nc\n```",
+        ]
+        .join("\n\n");
+        let result = get_test_cases(content, Options::default()).unwrap();
+        let flags = result
+            .iter()
+            .map(|c| c.options_inherited)
+            .collect::<Vec<_>>();
+        assert_eq!(flags, vec![false, true, false, true]);
+        assert_eq!(result[2].options, result[3].options);
+    }
+
+    #[test]
+    fn test_get_test_cases_layered() {
+        let content = "# A\n\n```toml options\nbar = false\n```\n\n```\na\n```".to_owned();
+        let layers = vec!["foo = 1\nbar = true".to_owned(), "foo = 2".to_owned()];
+        let result = get_test_cases_layered(content.clone(), Options::default(), layers).unwrap();
+        assert_eq!(result[0].options, Options { foo: 2, bar: false });
+        assert_eq!(result[0].options_trace.len(), 1);
+        let layers = vec!["foo = 1".to_owned(), "foo = ".to_owned()];
+        let result = get_test_cases_layered(content, Options::default(), layers);
+        assert!(matches!(
+            result,
+            Err(ParseError::OptionsLayer { index: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn test_root_line_number() {
+        let content = ["Intro", "```\na\n```", "```\nb\n```", "# A", "```\nc\n```"].join("\n\n");
+        let result = get_test_cases(content, Options::default()).unwrap();
+        let lines = result.iter().map(|c| c.line_number).collect::<Vec<_>>();
+        assert_eq!(lines, vec![3, 11]);
+    }
+
+    #[test]
+    fn test_fence_languages() {
+        let content = [
+            "# A",
+            "```yaml\nfoo: 1\n```",
+            "```diff\n-a\n+b\n```",
+            "```options\nfoo = 2\n```",
+            "```toml options\nfoo = 3\n```",
+            "```toml options setup\nbar = true\n```",
+            "```yaml setup\ns\n```",
+            "```toml options literal\nfoo = 4\n```",
+        ]
+        .join("\n\n");
+        let result = get_test_cases(content, Options::default()).unwrap();
+        assert_eq!(result[0].options, Options { foo: 3, bar: true });
+        assert_eq!(result[0].setup, vec!["s"]);
+        let langs = result[0]
+            .args
+            .iter()
+            .map(|a| a.lang.as_deref().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(langs, vec!["yaml", "diff", "options", "toml"]);
+    }
+
+    #[test]
+    fn test_get_test_cases_from_ast() {
+        let content = "# A\n\n```toml options\nfoo = 1\n```\n\n```\na\n```\n\n# B\n\n```\nb\n```";
+        let root = markdown::to_mdast(content, &Config::default().parse_options()).unwrap();
+        let result = get_test_cases_from_ast(root, Options::default()).unwrap();
+        assert_eq!(
+            result,
+            get_test_cases(content.to_owned(), Options::default()).unwrap()
+        );
+        let Node::Root(root) = markdown::to_mdast(content, &Default::default()).unwrap() else {
+            panic!("Expected a root node");
+        };
+        let heading = root.children[0].clone();
+        assert!(matches!(
+            get_test_cases_from_ast(heading, Options::default()),
+            Err(ParseError::Markdown(_))
+        ));
+    }
+
+    #[test]
+    fn test_boundary() {
+        let content = [
+            "# A",
+            "## B",
+            "```\nb\n```",
+            "### Detail",
+            "```\nc\n```",
+            "---",
+            "```\nd\n```",
+        ]
+        .join("\n\n");
+        let summary = |extractor: TestCaseExtractor<Options>| {
+            extractor
+                .parse(content.clone())
+                .unwrap()
+                .iter()
+                .map(|c| {
+                    let values = c.args.iter().map(|a| a.value.clone()).collect::<Vec<_>>();
+                    (c.full_name(), values.join(","))
+                })
+                .collect::<Vec<_>>()
+        };
+        let shallow = |node: &Node| matches!(node, Node::Heading(h) if h.depth <= 2);
+        assert_eq!(
+            summary(TestCaseExtractor::new(Options::default()).boundary(shallow)),
+            vec![("A > B".to_owned(), "b,c,d".to_owned())]
+        );
+        let by_break =
+            summary(TestCaseExtractor::new(Options::default()).boundary(ThematicBreakBoundary));
+        assert_eq!(
+            by_break,
+            vec![
+                ("A > B".to_owned(), "b".to_owned()),
+                ("A > B > Detail".to_owned(), "c".to_owned()),
+                ("A > B > Detail".to_owned(), "d".to_owned()),
+            ]
+        );
+        assert_eq!(
+            by_break,
+            summary(TestCaseExtractor::new(Options::default()).split_on_thematic_break(true))
+        );
+    }
+
+    #[test]
+    fn test_args_as_map() {
+        let content = [
+            "# A",
+            "```sql title=\"my query\"\nselect 1\n```",
+            "```json title=params\n[]\n```",
+            "```\npositional\n```",
+            "```json title=params\n[1]\n```",
+        ]
+        .join("\n\n");
+        let result = TestCaseExtractor::new(Options::default())
+            .args_as_map(true)
+            .strict(true)
+            .parse(content.clone())
+            .unwrap();
+        let named = result[0]
+            .named_args
+            .iter()
+            .map(|(k, v)| (k.as_str(), v.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(named, vec![("my query", "select 1"), ("params", "[1]")]);
+        assert_eq!(result[0].args, vec![arg("positional", 11)]);
+        let result = get_test_cases(content, Options::default()).unwrap();
+        assert_eq!(result[0].args.len(), 4);
+        assert!(result[0].named_args.is_empty());
+    }
+
+    #[test]
+    fn test_options_at_path() {
+        let path = PathBuf::from_iter([env!("CARGO_MANIFEST_DIR"), "src", "test.md"]);
+        let content = std::fs::read_to_string(path).unwrap();
+        let options = |path: &[&str]| options_at_path(content.clone(), Options::default(), path);
+        assert_eq!(options(&[]), Ok(Options::default()));
+        assert_eq!(options(&["Tests"]), Ok(Options { foo: 5, bar: true }));
+        assert_eq!(
+            options(&["Tests", "Fruits", "Pear"]),
+            Ok(Options { foo: 5, bar: false })
+        );
+        assert_eq!(
+            options(&["Tests", "Vegetables", "Potato"]),
+            Ok(Options { foo: 11, bar: true })
+        );
+        assert_eq!(
+            options(&["Tests", "Meat"]),
+            Err(ParseError::UnknownPath {
+                path: "Tests > Meat".to_owned()
+            })
+        );
+    }
+
+    #[test]
+    fn test_stable_id() {
+        let content = [
+            "# A",
+            "```\na\n```",
+            "## B",
+            "```\nb\n```",
+            "# A",
+            "```\nc\n```",
+        ]
+        .join("\n\n");
+        let result = get_test_cases(content, Options::default()).unwrap();
+        let ids = result.iter().map(|c| c.stable_id()).collect::<Vec<_>>();
+        assert_eq!(ids[0], "af63fc4c860222ec");
+        assert_eq!(ids[0], ids[2]);
+        assert_eq!(ids[1], "fb19e319a13aa891");
+        let moved = get_test_cases("Intro\n\n# A\n\n```\nx\n```".to_owned(), Options::default());
+        assert_eq!(moved.unwrap()[0].stable_id(), ids[0]);
+    }
+
+    #[test]
+    fn test_deep_scan() {
+        let content = [
+            "# A",
+            "1. Set up\n\n   ```toml options\n   foo = 2\n   ```\n\n2. Run\n\n   ```\n   a\n   ```",
+            "> ```\n> b\n> ```",
+            "> ## Quoted\n>\n> Not a section",
+            "# B",
+            "- ```\n  c\n  ```",
+        ]
+        .join("\n\n");
+        let result = get_test_cases(content.clone(), Options::default()).unwrap();
+        assert_eq!(result.len(), 0);
+        let (result, warnings) = TestCaseExtractor::new(Options::default())
+            .deep_scan(true)
+            .parse_with_warnings(content)
+            .unwrap();
+        let values = result
+            .iter()
+            .map(|c| {
+                (
+                    c.name.as_str(),
+                    c.args.iter().map(|a| a.value.as_str()).collect(),
+                )
+            })
+            .collect::<Vec<(_, Vec<_>)>>();
+        assert_eq!(values, vec![("A", vec!["a", "b"]), ("B", vec!["c"])]);
+        assert_eq!(result[0].options.foo, 2);
+        assert_eq!(result[0].args[0].line, 11);
+        assert_eq!(result[0].description, None);
+        assert!(warnings.is_empty());
+    }
+
+    #[test]
+    fn test_h1_starts_from_root_options() {
+        let content = [
+            "# A",
+            "```toml options\nfoo = 2\n```",
+            "## A1",
+            "```toml options\nbar = true\n```",
+            "```\na\n```",
+            "# B",
+            "```\nb\n```",
+            "## B1",
+            "```\nb1\n```",
+        ]
+        .join("\n\n");
+        let root = Options { foo: 1, bar: false };
+        let result = get_test_cases(content, root).unwrap();
+        let options = result.iter().map(|c| c.options).collect::<Vec<_>>();
+        assert_eq!(options, vec![Options { foo: 2, bar: true }, root, root]);
+    }
+
+    #[test]
+    fn test_position() {
+        let root = markdown::to_mdast("# A\n\n```\na\n```", &Default::default()).unwrap();
+        let code = &root.children().unwrap()[1];
+        let position = Position::from(code.position().unwrap());
+        assert_eq!(
+            position,
+            Position {
+                start: Point {
+                    line: 3,
+                    column: 1,
+                    offset: 5,
+                },
+                end: Point {
+                    line: 5,
+                    column: 4,
+                    offset: 14,
+                },
+            }
+        );
+        assert_eq!(Span::from(position), Span::from(code.position().unwrap()));
+    }
+
+    #[test]
+    fn test_max_args_per_case() {
+        let content = [
+            "# A",
+            "```\na\n```",
+            "# B",
+            "```\nb1\n```",
+            "```\nb2\n```",
+            "```\nb3\n```",
+        ]
+        .join("\n\n");
+        let extractor = TestCaseExtractor::new(Options::default()).max_args_per_case(2);
+        let (result, warnings) = extractor
+            .clone()
+            .parse_with_warnings(content.clone())
+            .unwrap();
+        assert_eq!(result[1].args.len(), 3);
+        assert_eq!(
+            warnings,
+            vec![Warning::TooManyArgs {
+                name: "B".to_owned(),
+                line: 7,
+                count: 3,
+            }]
+        );
+        let (result, warnings) = extractor
+            .truncate_args(true)
+            .parse_with_warnings(content)
+            .unwrap();
+        assert_eq!(result[1].args, vec![arg("b1", 9), arg("b2", 13)]);
+        assert_eq!(warnings.len(), 1);
+    }
 }
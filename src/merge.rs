@@ -0,0 +1,97 @@
+use crate::{ParseError, TestCase, TryMergeSerialized};
+use std::collections::HashMap;
+
+/// How [`merge_test_cases`] resolves a test case in the overlay with the same
+/// headings and name as one in the base.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MergeStrategy {
+    /// Replace the base test case with the overlay's.
+    #[default]
+    Overlay,
+    /// Keep both, with the overlay's right after the base's.
+    KeepBoth,
+    /// Fail with [`ParseError::DuplicateName`].
+    Error,
+}
+
+/// Combines the test cases of a base spec with those of an overlay, matching
+/// them by their headings and name. The result keeps the base's order, with
+/// conflicts resolved in place according to `strategy`, followed by the test
+/// cases only found in the overlay, in their own order.
+pub fn merge_test_cases<Options: TryMergeSerialized>(
+    base: Vec<TestCase<Options>>,
+    overlay: Vec<TestCase<Options>>,
+    strategy: MergeStrategy,
+) -> Result<Vec<TestCase<Options>>, ParseError> {
+    let mut index = HashMap::new();
+    for (i, case) in base.iter().enumerate() {
+        index.entry(key(case)).or_insert(i);
+    }
+    let mut slots = base.into_iter().map(|case| vec![case]).collect::<Vec<_>>();
+    let mut added = vec![];
+    for case in overlay {
+        let Some(&i) = index.get(&key(&case)) else {
+            added.push(case);
+            continue;
+        };
+        match strategy {
+            MergeStrategy::Overlay => slots[i] = vec![case],
+            MergeStrategy::KeepBoth => slots[i].push(case),
+            MergeStrategy::Error => {
+                return Err(ParseError::DuplicateName {
+                    name: case.full_name(),
+                    first_line: slots[i][0].line_number,
+                    second_line: case.line_number,
+                })
+            }
+        }
+    }
+    Ok(slots.into_iter().flatten().chain(added).collect())
+}
+
+fn key<Options: TryMergeSerialized>(case: &TestCase<Options>) -> Vec<String> {
+    case.headings.iter().chain([&case.name]).cloned().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{merge_test_cases, MergeStrategy};
+    use crate::{ParseError, TestCase};
+
+    fn test_case(headings: &[&str], name: &str, line_number: usize) -> TestCase<()> {
+        TestCase {
+            headings: headings.iter().map(|h| h.to_string()).collect(),
+            name: name.to_owned(),
+            line_number,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_merge_test_cases() {
+        let base = vec![
+            test_case(&["A"], "One", 1),
+            test_case(&["A"], "Two", 2),
+            test_case(&["B"], "One", 3),
+        ];
+        let overlay = vec![test_case(&["C"], "New", 10), test_case(&["A"], "Two", 20)];
+        let lines = |strategy| {
+            merge_test_cases(base.clone(), overlay.clone(), strategy)
+                .unwrap()
+                .iter()
+                .map(|c| c.line_number)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(lines(MergeStrategy::Overlay), vec![1, 20, 3, 10]);
+        assert_eq!(lines(MergeStrategy::KeepBoth), vec![1, 2, 20, 3, 10]);
+        let result = merge_test_cases(base, overlay, MergeStrategy::Error);
+        assert_eq!(
+            result,
+            Err(ParseError::DuplicateName {
+                name: "A > Two".to_owned(),
+                first_line: 2,
+                second_line: 20,
+            })
+        );
+    }
+}
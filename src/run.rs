@@ -0,0 +1,117 @@
+use crate::{TestCase, TryMergeSerialized};
+use std::fmt;
+
+/// The result of running one test case with [`run_cases`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CaseResult {
+    /// The headings and name of the test case, joined with `" > "`.
+    pub name: String,
+    pub line: usize,
+    pub outcome: CaseOutcome,
+}
+
+/// Whether a test case passed, failed or wasn't run. See [`CaseResult`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum CaseOutcome {
+    Passed,
+    /// The test function returned an error, formatted with `Display`.
+    Failed(String),
+    /// The test case was skipped, or another test case was focused.
+    Skipped,
+}
+
+impl fmt::Display for CaseResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.outcome {
+            CaseOutcome::Passed => write!(f, "PASS {} (line {})", self.name, self.line),
+            CaseOutcome::Failed(message) => {
+                write!(f, "FAIL {} (line {}): {}", self.name, self.line, message)
+            }
+            CaseOutcome::Skipped => write!(f, "SKIP {} (line {})", self.name, self.line),
+        }
+    }
+}
+
+/// Calls `f` with each test case, in order, collecting a [`CaseResult`] for
+/// each. Skipped test cases aren't run, and neither are unfocused ones when any
+/// test case is focused, but they still get a result so that counts stay stable.
+pub fn run_cases<Options, F, A>(cases: &[TestCase<Options>], mut f: F) -> Vec<CaseResult>
+where
+    Options: TryMergeSerialized,
+    F: FnMut(&TestCase<Options>) -> Result<(), A>,
+    A: fmt::Display,
+{
+    let any_focused = cases.iter().any(|c| c.focused);
+    cases
+        .iter()
+        .map(|case| {
+            let outcome = if case.skipped || (any_focused && !case.focused) {
+                CaseOutcome::Skipped
+            } else {
+                match f(case) {
+                    Ok(()) => CaseOutcome::Passed,
+                    Err(error) => CaseOutcome::Failed(error.to_string()),
+                }
+            };
+            CaseResult {
+                name: case.full_name(),
+                line: case.line_number,
+                outcome,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{run_cases, CaseOutcome, CaseResult};
+    use crate::get_test_cases;
+
+    #[test]
+    fn test_run_cases() {
+        let content = [
+            "# A",
+            "```\n1\n```",
+            "# B",
+            "```\nx\n```",
+            "# C <!-- skip -->",
+            "```\n3\n```",
+        ]
+        .join("\n\n");
+        let cases = get_test_cases(content, ()).unwrap();
+        let mut calls = 0;
+        let results = run_cases(&cases, |case| {
+            calls += 1;
+            case.args[0].value.parse::<i64>().map(|_| ())
+        });
+        assert_eq!(calls, 2);
+        let outcomes = results.iter().map(|r| &r.outcome).collect::<Vec<_>>();
+        assert_eq!(
+            outcomes,
+            vec![
+                &CaseOutcome::Passed,
+                &CaseOutcome::Failed("invalid digit found in string".to_owned()),
+                &CaseOutcome::Skipped,
+            ]
+        );
+        assert_eq!(
+            results[1].to_string(),
+            "FAIL B (line 7): invalid digit found in string"
+        );
+
+        let focused = "# A\n\n```\n1\n```\n\n# B <!-- only -->\n\n```\n2\n```".to_owned();
+        let cases = get_test_cases(focused, ()).unwrap();
+        let results = run_cases(&cases, |_| Ok::<(), String>(()));
+        assert_eq!(
+            results[0],
+            CaseResult {
+                name: "A".to_owned(),
+                line: 1,
+                outcome: CaseOutcome::Skipped,
+            }
+        );
+        assert_eq!(results[1].outcome, CaseOutcome::Passed);
+    }
+}
@@ -0,0 +1,52 @@
+use std::collections::HashMap;
+
+/// Generates GitHub-style heading anchors, disambiguating repeated headings
+/// within a document with a numeric suffix (`setup`, `setup-1`, ...).
+#[derive(Debug, Default)]
+pub struct Slugger {
+    occurrences: HashMap<String, usize>,
+}
+
+impl Slugger {
+    pub fn slug(&mut self, value: &str) -> String {
+        let original = slugify(value);
+        let mut result = original.clone();
+        while self.occurrences.contains_key(&result) {
+            let count = self.occurrences.entry(original.clone()).or_insert(0);
+            *count += 1;
+            result = format!("{}-{}", original, count);
+        }
+        self.occurrences.insert(result.clone(), 0);
+        result
+    }
+}
+
+/// Lowercases the value, strips punctuation and replaces spaces with hyphens.
+pub fn slugify(value: &str) -> String {
+    value
+        .to_lowercase()
+        .chars()
+        .filter(|c| c.is_alphanumeric() || matches!(c, ' ' | '-' | '_'))
+        .map(|c| if c == ' ' { '-' } else { c })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{slugify, Slugger};
+
+    #[test]
+    fn test_slugify() {
+        assert_eq!(slugify("Edge Cases (Weird!)"), "edge-cases-weird");
+        assert_eq!(slugify("`parse()` fn_name"), "parse-fn_name");
+    }
+
+    #[test]
+    fn test_duplicates() {
+        let mut slugger = Slugger::default();
+        assert_eq!(slugger.slug("Setup"), "setup");
+        assert_eq!(slugger.slug("Setup"), "setup-1");
+        assert_eq!(slugger.slug("Setup"), "setup-2");
+        assert_eq!(slugger.slug("Setup 1"), "setup-1-1");
+    }
+}
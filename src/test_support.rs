@@ -0,0 +1,8 @@
+use crate::MergeSerialized;
+
+/// Lets tests which don't care about options pass `()` as the root options.
+impl MergeSerialized for () {
+    fn merge_serialized(&self, _source: String) -> Result<Self, String> {
+        Ok(())
+    }
+}
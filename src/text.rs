@@ -0,0 +1,231 @@
+use markdown::mdast::Node;
+
+/// Renders inline nodes (such as the children of a heading) as plain text,
+/// returning `None` if any of them can't be represented as text.
+pub fn inline_text(nodes: &[Node]) -> Option<String> {
+    nodes.iter().map(node_to_plain_text).collect()
+}
+
+/// Concatenates the text within an inline node, e.g. `See the spec` for
+/// `See [the spec](http://x)`. Inline HTML tags are dropped, keeping the text
+/// between them, so `Press <kbd>Ctrl</kbd>` becomes `Press Ctrl`. Returns `None`
+/// for nodes like images which have no textual content.
+pub fn node_to_plain_text(node: &Node) -> Option<String> {
+    match node {
+        Node::Text(t) => Some(t.value.clone()),
+        Node::InlineCode(c) => Some(c.value.clone()),
+        Node::Emphasis(e) => inline_text(&e.children),
+        Node::Strong(s) => inline_text(&s.children),
+        Node::Link(l) => inline_text(&l.children),
+        Node::LinkReference(l) => inline_text(&l.children),
+        Node::Delete(d) => inline_text(&d.children),
+        Node::Html(_) => Some(String::new()),
+        _ => None,
+    }
+}
+
+/// Returns the trimmed body of an HTML comment like `<!-- skip -->`.
+pub fn comment_text(html: &str) -> Option<&str> {
+    let body = html.trim().strip_prefix("<!--")?.strip_suffix("-->")?;
+    Some(body.trim())
+}
+
+/// Parses `key: value` pairs separated by semicolons from the text of a
+/// comment like `<!-- tag: slow; timeout: 30 -->`. Parts without a colon are
+/// ignored.
+pub fn parse_directives(comment: &str) -> impl Iterator<Item = (String, String)> + '_ {
+    comment.split(';').filter_map(|part| {
+        let (key, value) = part.split_once(':')?;
+        Some((key.trim().to_string(), value.trim().to_string()))
+    })
+}
+
+/// Parses the comma-separated values of any `tags` directive in a comment like
+/// `<!-- tags: network, slow -->`.
+pub fn parse_tags(comment: &str) -> impl Iterator<Item = String> + '_ {
+    parse_directives(comment)
+        .filter(|(key, _)| key == "tags")
+        .flat_map(|(_, value)| {
+            value
+                .split(',')
+                .map(|tag| tag.trim().to_string())
+                .filter(|tag| !tag.is_empty())
+                .collect::<Vec<_>>()
+        })
+}
+
+/// Replaces `\r\n` line endings with `\n`.
+pub fn normalize_newlines(value: String) -> String {
+    if value.contains('\r') {
+        value.replace("\r\n", "\n")
+    } else {
+        value
+    }
+}
+
+/// Renders markdown as HTML, with GFM extensions such as tables enabled.
+#[cfg(feature = "render_html")]
+pub fn render_html(markdown: &str) -> Option<String> {
+    markdown::to_html_with_options(markdown, &markdown::Options::gfm()).ok()
+}
+
+/// Without the `render_html` feature, args aren't rendered.
+#[cfg(not(feature = "render_html"))]
+pub fn render_html(_markdown: &str) -> Option<String> {
+    None
+}
+
+/// Wraps `value` in a fenced code block, using more backticks than any run of
+/// them within the value.
+pub fn fence(lang: Option<&str>, meta: Option<&str>, value: &str) -> String {
+    let longest_run = value
+        .split(|c| c != '`')
+        .map(str::len)
+        .max()
+        .unwrap_or_default();
+    let ticks = "`".repeat(longest_run.max(2) + 1);
+    let info = [lang, meta]
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>()
+        .join(" ");
+    if value.is_empty() {
+        format!("{}{}\n{}", ticks, info, ticks)
+    } else {
+        format!("{}{}\n{}\n{}", ticks, info, value, ticks)
+    }
+}
+
+/// Splits the meta of a code block into tokens on whitespace, except within
+/// double quotes, so that `title="my query"` is a single token.
+pub fn meta_tokens(meta: &str) -> impl Iterator<Item = &str> {
+    let mut quoted = false;
+    meta.split(move |c: char| {
+        if c == '"' {
+            quoted = !quoted;
+        }
+        c.is_whitespace() && !quoted
+    })
+    .filter(|token| !token.is_empty())
+}
+
+/// The value of a `key="value"` token in the meta of a code block, without
+/// quotes. The value may contain whitespace if it's quoted.
+pub fn meta_value<'a>(meta: &'a str, key: &str) -> Option<&'a str> {
+    meta_tokens(meta).find_map(|token| {
+        let value = token.strip_prefix(key)?.strip_prefix('=')?;
+        Some(value.trim_matches('"'))
+    })
+}
+
+/// Splits the content of a code block on lines equal to `delimiter`. Each part
+/// comes with the number of lines between the code block's opening fence and
+/// the delimiter before the part, or 0 for the first part.
+pub fn split_on_delimiter(value: &str, delimiter: &str) -> Vec<(usize, String)> {
+    let mut parts = vec![];
+    let mut offset = 0;
+    let mut lines = vec![];
+    for (index, line) in value.split('\n').enumerate() {
+        if line == delimiter {
+            parts.push((offset, lines.join("\n")));
+            lines.clear();
+            offset = index + 1;
+        } else {
+            lines.push(line);
+        }
+    }
+    parts.push((offset, lines.join("\n")));
+    parts
+}
+
+/// Separates HTML comments from the rest of a heading's children, returning
+/// the remaining nodes along with the text of each comment.
+pub fn take_comments(children: Vec<Node>) -> (Vec<Node>, Vec<String>) {
+    let mut nodes = vec![];
+    let mut comments = vec![];
+    for child in children {
+        if let Node::Html(html) = &child {
+            if let Some(comment) = comment_text(&html.value) {
+                comments.push(comment.to_string());
+                continue;
+            }
+        }
+        nodes.push(child);
+    }
+    (nodes, comments)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{comment_text, inline_text, meta_tokens, meta_value, parse_directives, parse_tags};
+    use markdown::{mdast::Node, to_mdast, Constructs, ParseOptions};
+
+    /// Parses a single line of markdown as a heading, returning its children.
+    fn heading_children(markdown: &str) -> Vec<Node> {
+        let options = ParseOptions {
+            constructs: Constructs::gfm(),
+            ..ParseOptions::default()
+        };
+        let root = to_mdast(&format!("# {}", markdown), &options).unwrap();
+        let Some(Node::Heading(heading)) = root.children().unwrap().first().cloned() else {
+            panic!("Expected a heading");
+        };
+        heading.children
+    }
+
+    fn plain_text(markdown: &str) -> Option<String> {
+        inline_text(&heading_children(markdown))
+    }
+
+    #[test]
+    fn test_inline_types() {
+        assert_eq!(plain_text("plain").as_deref(), Some("plain"));
+        assert_eq!(plain_text("`code`").as_deref(), Some("code"));
+        assert_eq!(plain_text("_em_").as_deref(), Some("em"));
+        assert_eq!(plain_text("**strong**").as_deref(), Some("strong"));
+        assert_eq!(plain_text("[link](http://x)").as_deref(), Some("link"));
+        assert_eq!(plain_text("~~delete~~").as_deref(), Some("delete"));
+        assert_eq!(
+            plain_text("See [the spec](http://x) for _details_").as_deref(),
+            Some("See the spec for details")
+        );
+        assert_eq!(
+            plain_text("**bold _and `nested`_**").as_deref(),
+            Some("bold and nested")
+        );
+        assert_eq!(
+            plain_text("Press <kbd>Ctrl</kbd>").as_deref(),
+            Some("Press Ctrl")
+        );
+        assert_eq!(plain_text("![image](x.png)"), None);
+    }
+
+    #[test]
+    fn test_comments() {
+        assert_eq!(comment_text("<!-- skip -->"), Some("skip"));
+        assert_eq!(comment_text("<div>"), None);
+        let directives = parse_directives("tag: slow; timeout : 30; ignored").collect::<Vec<_>>();
+        assert_eq!(
+            directives,
+            vec![
+                ("tag".to_owned(), "slow".to_owned()),
+                ("timeout".to_owned(), "30".to_owned())
+            ]
+        );
+        let tags = parse_tags("tags: network, slow,; owner: me").collect::<Vec<_>>();
+        assert_eq!(tags, vec!["network".to_owned(), "slow".to_owned()]);
+    }
+
+    #[test]
+    fn test_meta_value() {
+        let meta = r#"options file="a.toml" title=query"#;
+        assert_eq!(meta_value(meta, "file"), Some("a.toml"));
+        assert_eq!(meta_value(meta, "title"), Some("query"));
+        assert_eq!(meta_value(meta, "options"), None);
+        assert_eq!(meta_value("titles=x", "title"), None);
+        let meta = r#"sql title="my query"  expected"#;
+        assert_eq!(meta_value(meta, "title"), Some("my query"));
+        let tokens = meta_tokens(meta).collect::<Vec<_>>();
+        assert_eq!(tokens, vec!["sql", r#"title="my query""#, "expected"]);
+    }
+}
@@ -0,0 +1,99 @@
+use crate::{MergeSerialized, SerializeOptions};
+use toml::{Table, Value};
+
+/// Parses the options block as TOML and deep-merges it onto the inherited
+/// options.
+impl MergeSerialized for Table {
+    fn merge_serialized(&self, source: String) -> Result<Self, String> {
+        let patch = source.parse::<Table>().map_err(|e| e.to_string())?;
+        let mut merged = self.clone();
+        merge(&mut merged, patch);
+        Ok(merged)
+    }
+}
+
+/// Emits only the keys which differ from `base`, recursing into nested tables.
+/// Keys missing from `self` can't be expressed as a merge, so they're left out.
+impl SerializeOptions for Table {
+    const LANG: &'static str = "toml";
+
+    fn serialize_options(&self, base: &Self) -> Option<String> {
+        let patch = diff(base, self);
+        (!patch.is_empty()).then(|| patch.to_string().trim_end().to_string())
+    }
+}
+
+/// The smallest table which [`merge`] can apply to `base` to get `target`.
+fn diff(base: &Table, target: &Table) -> Table {
+    let mut patch = Table::new();
+    for (key, value) in target {
+        match (base.get(key), value) {
+            (Some(existing), value) if existing == value => {}
+            (Some(Value::Table(existing)), Value::Table(value)) => {
+                patch.insert(key.clone(), Value::Table(diff(existing, value)));
+            }
+            (_, value) => {
+                patch.insert(key.clone(), value.clone());
+            }
+        }
+    }
+    patch
+}
+
+/// Merges tables key by key, recursing into nested tables. Any other value in
+/// `patch`, including arrays, replaces the value in `base`.
+fn merge(base: &mut Table, patch: Table) {
+    for (key, value) in patch {
+        match (base.get_mut(&key), value) {
+            (Some(Value::Table(existing)), Value::Table(value)) => merge(existing, value),
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{MergeSerialized, SerializeOptions};
+    use toml::Table;
+
+    fn table(source: &str) -> Table {
+        source.parse::<Table>().unwrap()
+    }
+
+    #[test]
+    fn test_deep_merge() {
+        let base = table("a = 1\n[b]\nc = 2\n[b.d]\ne = 3");
+        let merged = base.merge_serialized("[b.d]\nf = 4".to_owned()).unwrap();
+        assert_eq!(merged, table("a = 1\n[b]\nc = 2\n[b.d]\ne = 3\nf = 4"));
+    }
+
+    #[test]
+    fn test_scalar_override() {
+        let base = table("a = 1\nlist = [1, 2]\n[b]\nc = 2");
+        let merged = base
+            .merge_serialized("a = \"x\"\nlist = [3]\nb = false".to_owned())
+            .unwrap();
+        assert_eq!(merged, table("a = \"x\"\nlist = [3]\nb = false"));
+    }
+
+    #[test]
+    fn test_serialize_options() {
+        let base = table("a = 1\nlist = [1]\n[b]\nc = 2\nd = 3");
+        let target = table("a = 1\nlist = [1, 2]\n[b]\nc = 2\nd = 4\n[e]\nf = true");
+        let source = target.serialize_options(&base).unwrap();
+        assert_eq!(
+            table(&source),
+            table("list = [1, 2]\n[b]\nd = 4\n[e]\nf = true")
+        );
+        assert_eq!(base.merge_serialized(source).unwrap(), target);
+        assert_eq!(base.serialize_options(&base), None);
+    }
+
+    #[test]
+    fn test_invalid_toml() {
+        let result = Table::new().merge_serialized("a = ".to_owned());
+        assert!(result.is_err());
+    }
+}
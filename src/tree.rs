@@ -0,0 +1,139 @@
+use crate::{TestCase, TryMergeSerialized};
+use std::collections::BTreeMap;
+
+/// Test cases grouped by heading. See [`build_tree`].
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TestTree<Options: TryMergeSerialized> {
+    /// The text of the heading, or an empty string for the root.
+    pub name: String,
+    /// Nested headings, in the order they first appear.
+    pub children: Vec<TestTree<Options>>,
+    /// The test cases directly under this heading.
+    pub cases: Vec<TestCase<Options>>,
+}
+
+impl<Options: TryMergeSerialized> TestTree<Options> {
+    fn new(name: String) -> Self {
+        TestTree {
+            name,
+            children: vec![],
+            cases: vec![],
+        }
+    }
+
+    fn child(&mut self, name: &str) -> &mut TestTree<Options> {
+        let index = match self.children.iter().position(|c| c.name == name) {
+            Some(index) => index,
+            None => {
+                self.children.push(TestTree::new(name.to_string()));
+                self.children.len() - 1
+            }
+        };
+        &mut self.children[index]
+    }
+}
+
+/// Groups test cases into a tree following their headings. Each case is
+/// attached to the node for its own heading, and test cases from before the
+/// first heading are attached to the root.
+pub fn build_tree<Options: TryMergeSerialized>(cases: Vec<TestCase<Options>>) -> TestTree<Options> {
+    let mut root = TestTree::new(String::new());
+    for case in cases {
+        let mut node = &mut root;
+        for heading in &case.headings {
+            node = node.child(heading);
+        }
+        if case.depth > 0 {
+            node = node.child(&case.name);
+        }
+        node.cases.push(case);
+    }
+    root
+}
+
+/// Counts the test cases under each top-level heading, including test cases
+/// of the top-level heading itself. Test cases from before the first heading
+/// are counted under `root_key`, e.g. `"(root)"`.
+pub fn summarize<Options: TryMergeSerialized>(
+    cases: &[TestCase<Options>],
+    root_key: &str,
+) -> BTreeMap<String, usize> {
+    let mut counts = BTreeMap::new();
+    for case in cases {
+        let key = match case.headings.first() {
+            Some(heading) => heading.as_str(),
+            None if case.depth > 0 => case.name.as_str(),
+            None => root_key,
+        };
+        *counts.entry(key.to_string()).or_insert(0) += 1;
+    }
+    counts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{build_tree, summarize};
+    use crate::get_test_cases;
+
+    #[test]
+    fn test_build_tree() {
+        let content = [
+            "```\nroot\n```",
+            "# A",
+            "```\na\n```",
+            "### B",
+            "```\nb\n```",
+            "## C",
+            "#### D",
+            "```\nd\n```",
+            "# A",
+            "## E",
+            "```\ne\n```",
+        ]
+        .join("\n\n");
+        let tree = build_tree(get_test_cases(content, ()).unwrap());
+        assert_eq!(tree.cases.len(), 1);
+        assert_eq!(tree.children.len(), 1);
+        let a = &tree.children[0];
+        assert_eq!(a.name, "A");
+        assert_eq!(a.cases[0].args[0].value, "a");
+        let names = a
+            .children
+            .iter()
+            .map(|c| c.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["B", "C", "E"]);
+        assert_eq!(a.children[0].cases[0].args[0].value, "b");
+        assert!(a.children[1].cases.is_empty());
+        assert_eq!(a.children[1].children[0].name, "D");
+        assert_eq!(a.children[1].children[0].cases[0].args[0].value, "d");
+        assert_eq!(a.children[2].cases[0].args[0].value, "e");
+    }
+
+    #[test]
+    fn test_summarize() {
+        let content = [
+            "```\nroot\n```",
+            "# B",
+            "```\nb\n```",
+            "## C",
+            "```\nc\n```",
+            "### D",
+            "```\nd\n```",
+            "# A",
+            "## E",
+            "```\ne\n```",
+            "# B",
+            "```\nb\n```",
+        ]
+        .join("\n\n");
+        let counts = summarize(&get_test_cases(content, ()).unwrap(), "(root)");
+        let counts = counts
+            .iter()
+            .map(|(k, v)| (k.as_str(), *v))
+            .collect::<Vec<_>>();
+        assert_eq!(counts, vec![("(root)", 1), ("A", 1), ("B", 4)]);
+        assert!(summarize::<()>(&[], "(root)").is_empty());
+    }
+}
@@ -0,0 +1,100 @@
+use std::fmt;
+
+/// A problem with a document which doesn't stop test cases from being
+/// extracted. See [`crate::TestCaseExtractor::parse_with_warnings`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Warning {
+    /// An options block in a section with no test cases, including under its
+    /// child headings.
+    UnusedOptions { line: usize },
+    /// Two test cases have the same headings and name. This is an error
+    /// instead with [`crate::Config::detect_duplicates`].
+    DuplicateName {
+        /// The headings and name of the test cases, joined with `" > "`.
+        name: String,
+        first_line: usize,
+        second_line: usize,
+    },
+    /// A test case was removed by [`crate::dedupe_test_cases`] for being
+    /// semantically equal to the one before it.
+    DuplicateCase {
+        /// The headings and name of the test cases, joined with `" > "`.
+        name: String,
+        first_line: usize,
+        second_line: usize,
+    },
+    /// A heading with no text.
+    EmptyHeading { line: usize },
+    /// A test case has more args than [`crate::Config::max_args_per_case`].
+    TooManyArgs {
+        /// The headings and name of the test case, joined with `" > "`.
+        name: String,
+        line: usize,
+        /// The number of args, before any were dropped by
+        /// [`crate::Config::truncate_args`].
+        count: usize,
+    },
+    /// An options block couldn't be merged, with
+    /// [`crate::Config::continue_on_error`] enabled.
+    OptionsMerge {
+        line: usize,
+        /// The error returned by [`crate::TryMergeSerialized::try_merge_serialized`].
+        message: String,
+    },
+}
+
+impl Warning {
+    /// The line the warning refers to.
+    pub fn line(&self) -> usize {
+        match self {
+            Warning::UnusedOptions { line } => *line,
+            Warning::DuplicateName { second_line, .. } => *second_line,
+            Warning::DuplicateCase { second_line, .. } => *second_line,
+            Warning::EmptyHeading { line } => *line,
+            Warning::TooManyArgs { line, .. } => *line,
+            Warning::OptionsMerge { line, .. } => *line,
+        }
+    }
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Warning::UnusedOptions { line } => write!(
+                f,
+                "Options block at line {} has no test cases to apply to",
+                line
+            ),
+            Warning::DuplicateName {
+                name,
+                first_line,
+                second_line,
+            } => write!(
+                f,
+                "Duplicate test case \"{}\" at lines {} and {}",
+                name, first_line, second_line
+            ),
+            Warning::DuplicateCase {
+                name,
+                first_line,
+                second_line,
+            } => write!(
+                f,
+                "Removed test case \"{}\" at line {}, identical to the one at line {}",
+                name, second_line, first_line
+            ),
+            Warning::EmptyHeading { line } => write!(f, "Empty heading at line {}", line),
+            Warning::TooManyArgs { name, line, count } => write!(
+                f,
+                "Test case \"{}\" at line {} has {} args",
+                name, line, count
+            ),
+            Warning::OptionsMerge { line, message } => write!(
+                f,
+                "Ignored options from code block at line {}: {}",
+                line, message
+            ),
+        }
+    }
+}